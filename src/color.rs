@@ -0,0 +1,58 @@
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+
+/// The colors status messages are printed in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    /// For messages about something that succeeded.
+    Green,
+    /// For warnings and questions that need an answer.
+    Yellow,
+    /// For errors.
+    Red,
+}
+
+/// Wraps the message in the escape codes for the color, unless `$NO_COLOR`
+/// is set or stderr, where every message goes, isn't a terminal.
+pub fn paint(color: Color, message: impl Display) -> String {
+    paint_if(enabled(), color, message)
+}
+
+// https://no-color.org asks for color to be off when NO_COLOR is set to
+// anything but an empty string
+fn enabled() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stderr().is_terminal()
+}
+
+fn paint_if(enabled: bool, color: Color, message: impl Display) -> String {
+    if !enabled {
+        return message.to_string();
+    }
+
+    let code = match color {
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Red => 31,
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_if() {
+        let cases = vec![
+            (true, Color::Green, "\x1b[32mok\x1b[0m"),
+            (true, Color::Yellow, "\x1b[33mok\x1b[0m"),
+            (true, Color::Red, "\x1b[31mok\x1b[0m"),
+            (false, Color::Red, "ok"),
+        ];
+
+        for (enabled, color, expected) in cases {
+            assert_eq!(paint_if(enabled, color, "ok"), expected);
+        }
+    }
+}
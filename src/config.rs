@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+use gc_rust::ApplicationError;
+
+// Defaults read from the config file. Values are kept as written and parsed
+// like their command line counterparts, so they're validated the same way.
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub base_dir: Option<String>,
+    pub protocol: Option<String>,
+    pub depth: Option<String>,
+    pub host: Option<String>,
+    pub layout: Option<String>,
+    pub post_clone: Option<String>,
+    pub aliases: BTreeMap<String, String>,
+    pub profiles: BTreeMap<String, Config>,
+}
+
+// The table the keys being parsed go into.
+enum Table {
+    Defaults,
+    Aliases,
+    Profile(String),
+}
+
+impl Config {
+    // Loads the given config file, or $XDG_CONFIG_HOME/gc/config.toml, or
+    // ~/.config/gc/config.toml. Not having the default one is the same as
+    // having an empty one, but one asked for by path has to exist.
+    pub fn load(explicit: Option<&str>) -> Result<Config, ApplicationError> {
+        let Some(path) = explicit.map(PathBuf::from).or_else(config_path) else {
+            return Ok(Config::default());
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && explicit.is_none() => {
+                return Ok(Config::default())
+            }
+            Err(err) => {
+                return Err(ApplicationError::FailedReadingConfig(
+                    path.display().to_string(),
+                    err,
+                ))
+            }
+        };
+
+        Config::parse(&contents)
+            .map_err(|err| ApplicationError::InvalidConfig(path.display().to_string(), err))
+    }
+
+    // Applies the settings of a [profiles.NAME] table on top of the
+    // defaults, so whatever the profile doesn't set is kept.
+    pub fn with_profile(mut self, name: &str) -> Result<Config, ApplicationError> {
+        let profile = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| ApplicationError::UnknownProfile(name.to_string()))?;
+
+        self.base_dir = profile.base_dir.or(self.base_dir);
+        self.protocol = profile.protocol.or(self.protocol);
+        self.depth = profile.depth.or(self.depth);
+        self.host = profile.host.or(self.host);
+        self.layout = profile.layout.or(self.layout);
+        self.post_clone = profile.post_clone.or(self.post_clone);
+        Ok(self)
+    }
+
+    // Parses the small subset of TOML the config needs: comments, keys set
+    // to strings or integers, the [aliases] table of host aliases and the
+    // [profiles.NAME] tables of settings to pick from.
+    fn parse(contents: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut table = Table::Defaults;
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let name = match header.split_once(']') {
+                    Some((name, rest)) if parse_comment(rest).is_ok() => name,
+                    _ => return Err(format!("line {}: unknown table {}", number + 1, line)),
+                };
+                table = match name.strip_prefix("profiles.") {
+                    _ if name == "aliases" => Table::Aliases,
+                    Some(profile) if !profile.is_empty() => {
+                        // Even an empty profile can be picked
+                        config.profiles.entry(profile.to_string()).or_default();
+                        Table::Profile(profile.to_string())
+                    }
+                    _ => return Err(format!("line {}: unknown table {}", number + 1, line)),
+                };
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected a key = value pair", number + 1))?;
+            let value =
+                parse_value(value.trim()).map_err(|err| format!("line {}: {}", number + 1, err))?;
+
+            let target = match &table {
+                Table::Defaults => &mut config,
+                Table::Aliases => {
+                    config.aliases.insert(key.trim().to_string(), value);
+                    continue;
+                }
+                Table::Profile(name) => config.profiles.entry(name.clone()).or_default(),
+            };
+
+            let field = match key.trim() {
+                "base_dir" => &mut target.base_dir,
+                "protocol" => &mut target.protocol,
+                "depth" => &mut target.depth,
+                "host" => &mut target.host,
+                "layout" => &mut target.layout,
+                "post_clone" => &mut target.post_clone,
+                key => return Err(format!("line {}: unknown key {:?}", number + 1, key)),
+            };
+            *field = Some(value);
+        }
+
+        Ok(config)
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let config_home = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok().filter(|home| !home.is_empty())?).join(".config"),
+    };
+
+    Some(config_home.join("gc").join("config.toml"))
+}
+
+// Parses a value, which is either a quoted string or an integer, followed by
+// an optional comment.
+fn parse_value(value: &str) -> Result<String, String> {
+    let (value, rest) = match value.chars().next() {
+        Some('"') => parse_basic_string(&value[1..])?,
+        Some('\'') => {
+            let end = value[1..].find('\'').ok_or("unterminated string")?;
+            (value[1..end + 1].to_string(), &value[end + 2..])
+        }
+        Some(c) if c.is_ascii_digit() => {
+            let end = value
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(value.len());
+            (value[..end].to_string(), &value[end..])
+        }
+        _ => return Err("expected a string or a number".to_string()),
+    };
+
+    parse_comment(rest)?;
+    Ok(value)
+}
+
+// Makes sure nothing but a comment follows a value or a table header.
+fn parse_comment(rest: &str) -> Result<(), String> {
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected {:?}", rest));
+    }
+
+    Ok(())
+}
+
+// Parses the rest of a double-quoted string, returning it along with what
+// follows the closing quote.
+fn parse_basic_string(value: &str) -> Result<(String, &str), String> {
+    let mut parsed = String::new();
+    let mut chars = value.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((parsed, &value[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, '"')) => parsed.push('"'),
+                Some((_, '\\')) => parsed.push('\\'),
+                Some((_, 'n')) => parsed.push('\n'),
+                Some((_, 't')) => parsed.push('\t'),
+                _ => return Err("unsupported escape sequence".to_string()),
+            },
+            c => parsed.push(c),
+        }
+    }
+
+    Err("unterminated string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let contents = r#"
+# Where everything goes
+base_dir = "/home/user/code"
+protocol = 'https' # no SSH keys here
+depth = 1
+host = "gitlab.com"
+layout = "{host}/{team}/{project}"
+post_clone = "direnv allow"
+
+[aliases] # short names for hosts
+gh = "github.com"
+work = "git.internal.example.com"
+
+[profiles.work]
+base_dir = "/home/user/work"
+host = "git.internal.example.com"
+
+[profiles.empty]
+"#;
+
+        let expected = Config {
+            base_dir: Some("/home/user/code".to_string()),
+            protocol: Some("https".to_string()),
+            depth: Some("1".to_string()),
+            host: Some("gitlab.com".to_string()),
+            layout: Some("{host}/{team}/{project}".to_string()),
+            post_clone: Some("direnv allow".to_string()),
+            aliases: BTreeMap::from([
+                ("gh".to_string(), "github.com".to_string()),
+                ("work".to_string(), "git.internal.example.com".to_string()),
+            ]),
+            profiles: BTreeMap::from([
+                (
+                    "work".to_string(),
+                    Config {
+                        base_dir: Some("/home/user/work".to_string()),
+                        host: Some("git.internal.example.com".to_string()),
+                        ..Config::default()
+                    },
+                ),
+                ("empty".to_string(), Config::default()),
+            ]),
+        };
+        assert_eq!(Config::parse(contents).unwrap(), expected);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let cases = vec![
+            (r#""value""#, Some("value")),
+            (
+                r#""with \"quotes\" and \\""#,
+                Some(r#"with "quotes" and \"#),
+            ),
+            (r#""C:\path""#, None),
+            (r"'C:\path'", Some(r"C:\path")),
+            ("42 # a comment", Some("42")),
+            (r#""value" trailing"#, None),
+            (r#""unterminated"#, None),
+            ("true", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_value(input).ok().as_deref(),
+                expected,
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = vec![
+            "base_dir",
+            "unknown = 1",
+            "[section]",
+            "[aliases",
+            "[aliases] gh",
+            "[profiles.]",
+            "[profiles.work]\n[aliases.work]",
+            "depth = -1",
+        ];
+
+        for input in cases {
+            assert!(Config::parse(input).is_err(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_with_profile() {
+        let contents = r#"
+base_dir = "/home/user/code"
+protocol = "https"
+
+[profiles.work]
+base_dir = "/home/user/work"
+"#;
+
+        let cases = vec![
+            ("work", Some((Some("/home/user/work"), Some("https")))),
+            ("personal", None),
+        ];
+
+        for (name, expected) in cases {
+            let resolved = Config::parse(contents).unwrap().with_profile(name).ok();
+            let resolved = resolved
+                .as_ref()
+                .map(|c| (c.base_dir.as_deref(), c.protocol.as_deref()));
+            assert_eq!(resolved, expected, "profile: {}", name);
+        }
+    }
+
+    #[test]
+    fn test_load_explicit() {
+        let dir = env::temp_dir().join(format!("gc-rust-test-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let valid = dir.join("valid.toml");
+        fs::write(&valid, "depth = 1\n").unwrap();
+        let invalid = dir.join("invalid.toml");
+        fs::write(&invalid, "depth\n").unwrap();
+
+        let cases = vec![
+            (valid, Some(Some("1".to_string()))),
+            (invalid, None),
+            (dir.join("missing.toml"), None),
+        ];
+
+        for (path, expected) in cases {
+            let path = path.display().to_string();
+            let config = Config::load(Some(&path));
+            assert_eq!(config.ok().map(|c| c.depth), expected, "path: {}", path);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
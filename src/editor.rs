@@ -0,0 +1,69 @@
+use std::env;
+
+use subprocess::{Exec, Redirection};
+
+use gc_rust::ApplicationError;
+
+// Opens the repository in the editor from $VISUAL or $EDITOR, waiting for it
+// like git does so terminal editors get the terminal. Returns false when no
+// editor is set, and there's no system default to fall back to.
+pub fn open(path: &str) -> Result<bool, ApplicationError> {
+    let command = match editor(env::var("VISUAL").ok(), env::var("EDITOR").ok()) {
+        // The editor may come with its own arguments, like "code --wait", so
+        // it's run through the shell with the path kept out of the command
+        Some(editor) if cfg!(windows) => Exec::cmd("cmd")
+            .arg("/C")
+            .arg(format!("{} \"%GC_REPO_PATH%\"", editor)),
+        Some(editor) => Exec::cmd("sh")
+            .arg("-c")
+            .arg(format!("{} \"$GC_REPO_PATH\"", editor)),
+        // Windows opens folders with whatever is set up for them
+        None if cfg!(windows) => Exec::cmd("cmd").args(&["/C", "start", "", path]),
+        None => return Ok(false),
+    };
+
+    // stdout is left for the path, so editors write to the terminal through
+    // stderr instead
+    let status = command
+        .env("GC_REPO_PATH", path)
+        .stdout(Redirection::Merge)
+        .join()
+        .map_err(ApplicationError::FailedOpenEditorCommand)?;
+
+    if !status.success() {
+        return Err(ApplicationError::EditorFailed(status));
+    }
+
+    Ok(true)
+}
+
+// $VISUAL is meant for full-screen editors and wins over $EDITOR, like it
+// does for git.
+fn editor(visual: Option<String>, editor: Option<String>) -> Option<String> {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|editor| editor.trim().to_string())
+        .find(|editor| !editor.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor() {
+        let some = |editor: &str| Some(editor.to_string());
+        let cases = vec![
+            (some("code --wait"), some("vim"), Some("code --wait")),
+            (None, some("vim"), Some("vim")),
+            (some(""), some("nano"), Some("nano")),
+            (some("  "), None, None),
+            (None, None, None),
+        ];
+
+        for (visual, editor_var, expected) in cases {
+            assert_eq!(editor(visual, editor_var).as_deref(), expected);
+        }
+    }
+}
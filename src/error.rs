@@ -0,0 +1,464 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::git::describe_exit_status;
+use crate::parser;
+
+#[derive(Debug)]
+pub enum ApplicationError {
+    BaseDirNotFound,
+    BaseDirCannotBeOpened(std::io::Error),
+    CantCreateBaseDir(String, std::io::Error),
+    BaseDirNotWritable(String, std::io::Error),
+    FailedReadingConfig(String, std::io::Error),
+    InvalidConfig(String, String),
+    UnknownProfile(String),
+    InvalidOutputDir(String, std::io::Error),
+    InvalidName(String),
+    InvalidPrefix(String),
+    CantCreateTargetDir(std::io::Error),
+    CantDeleteTargetDir(std::io::Error),
+    CantRenameTargetDir(std::io::Error),
+    GitNotFound,
+    GitBinaryNotFound(String),
+    FailedCloneCommand(subprocess::PopenError),
+    FailedGitOperation(subprocess::ExitStatus),
+    FailedUpdateCommand(subprocess::PopenError),
+    FailedUpdate(subprocess::ExitStatus),
+    FailedCheckoutCommand(subprocess::PopenError),
+    NotAGitRepository(String),
+    NotShallow(String),
+    FailedUnshallowCommand(subprocess::PopenError),
+    FailedUnshallow(subprocess::ExitStatus),
+    FailedCheckout(String, subprocess::ExitStatus),
+    InvalidCommit(String),
+    FailedSparseCheckoutCommand(subprocess::PopenError),
+    FailedSparseCheckout(subprocess::ExitStatus),
+    FailedWorktreeCommand(subprocess::PopenError),
+    FailedWorktree(String, subprocess::ExitStatus),
+    FailedPostCloneHookCommand(subprocess::PopenError),
+    FailedOpenEditorCommand(subprocess::PopenError),
+    EditorFailed(subprocess::ExitStatus),
+    PostCloneHookFailed(subprocess::ExitStatus),
+    FailedParsingRepo(parser::ParseRepoError),
+    FailedCaptureInput(std::io::Error),
+    FailedReadingStdin(std::io::Error),
+    ArgumentParsingError(getopts::Fail),
+    InvalidDepth(String),
+    InvalidBranch(String, String),
+    InvalidSince(String),
+    InvalidFilter(String),
+    InvalidTimeout(String),
+    InvalidRetries(String),
+    InvalidJobs(String),
+    CloneTimedOut(std::time::Duration),
+    ConflictingOptions(String, String),
+    UnsupportedShell(String),
+    InvalidProtocol(String),
+    InvalidLogLevel(String),
+    InvalidCloneArgs(String),
+    InvalidLayout(String),
+    LayoutVariableNotSet(String, String),
+    DestinationExists(String),
+    CantConfirmDeletion(String),
+    Cancelled(String),
+    NothingPicked,
+    BranchNotFound(String),
+    TagNotFound(String),
+    TagBeforeSince(String, String),
+    DefaultBranchNotFound(String),
+    FailedFetchDefaultBranchCommand(subprocess::PopenError),
+    FailedFetchDefaultBranch(String, subprocess::ExitStatus),
+    OutputWithMultipleRepos,
+    OpenWithMultipleRepos,
+    StdinWithArguments,
+    FailedRepositories(usize, usize),
+}
+
+impl Display for ApplicationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ApplicationError::BaseDirNotFound => {
+                write!(f, "The base directory on which to download the repositories was not found. It's read from $GC_DOWNLOAD_PATH, then $GOPATH, then the gc.downloadPath git setting, falling back to $HOME/git, but none of them are set.")
+            }
+            ApplicationError::BaseDirCannotBeOpened(err) => {
+                write!(f, "Base directory cannot be opened: {}", err)
+            }
+            ApplicationError::CantCreateBaseDir(path, err) => {
+                write!(f, "Cannot create base directory {}: {}", path, err)
+            }
+            ApplicationError::BaseDirNotWritable(path, err) => {
+                write!(f, "Base directory {} is not writable: {}", path, err)
+            }
+            ApplicationError::FailedReadingConfig(path, err) => {
+                write!(f, "Cannot read config file {}: {}", path, err)
+            }
+            ApplicationError::InvalidConfig(path, err) => {
+                write!(f, "Invalid config file {}: {}", path, err)
+            }
+            ApplicationError::UnknownProfile(name) => {
+                write!(
+                    f,
+                    "Unknown profile {:?}: add a [profiles.{}] table to the config file.",
+                    name, name
+                )
+            }
+            ApplicationError::InvalidOutputDir(path, err) => {
+                write!(f, "Invalid output directory {}: {}", path, err)
+            }
+            ApplicationError::InvalidPrefix(prefix) => {
+                write!(
+                    f,
+                    "Invalid path prefix {:?}: it must be one or more directories, without \"..\".",
+                    prefix
+                )
+            }
+            ApplicationError::InvalidName(name) => {
+                write!(
+                    f,
+                    "Invalid directory name {:?}: it must be a single directory, without slashes.",
+                    name
+                )
+            }
+            ApplicationError::CantCreateTargetDir(err) => {
+                write!(f, "Cannot create target directory: {}", err)
+            }
+            ApplicationError::CantDeleteTargetDir(err) => {
+                write!(f, "Cannot delete target directory: {}", err)
+            }
+            ApplicationError::CantRenameTargetDir(err) => {
+                write!(
+                    f,
+                    "Cannot move the existing target directory aside: {}",
+                    err
+                )
+            }
+            ApplicationError::GitNotFound => {
+                write!(
+                    f,
+                    "git is required but was not found on PATH. Install it or point $GC_GIT_BINARY to it."
+                )
+            }
+            ApplicationError::GitBinaryNotFound(binary) => {
+                write!(
+                    f,
+                    "Cannot find an executable git binary at {:?}. Install git or point $GC_GIT_BINARY to it.",
+                    binary
+                )
+            }
+            ApplicationError::FailedCloneCommand(err) => {
+                write!(f, "Failed to run the git clone command: {}", err)
+            }
+            ApplicationError::FailedGitOperation(status) => {
+                write!(
+                    f,
+                    "Failed to clone the repo: git exited with {}.",
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::FailedUpdateCommand(err) => {
+                write!(f, "Failed to run the git pull command: {}", err)
+            }
+            ApplicationError::FailedUpdate(status) => {
+                write!(
+                    f,
+                    "Failed to update the repo: git exited with {}.",
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::FailedCaptureInput(err) => {
+                write!(f, "Failed to capture prompt: {}", err)
+            }
+            ApplicationError::FailedReadingStdin(err) => {
+                write!(f, "Failed to read repositories from stdin: {}", err)
+            }
+            ApplicationError::NotAGitRepository(path) => {
+                write!(f, "{} is not a git repository.", path)
+            }
+            ApplicationError::NotShallow(path) => {
+                write!(
+                    f,
+                    "{} already has the full history, it wasn't cloned with --depth.",
+                    path
+                )
+            }
+            ApplicationError::FailedUnshallowCommand(err) => {
+                write!(f, "Failed to run the git fetch command: {}", err)
+            }
+            ApplicationError::FailedUnshallow(status) => {
+                write!(
+                    f,
+                    "Failed to fetch the full history: git exited with {}.",
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::FailedCheckoutCommand(err) => {
+                write!(f, "Failed to run the git checkout command: {}", err)
+            }
+            ApplicationError::FailedCheckout(commit, status) => {
+                write!(
+                    f,
+                    "Failed to check out commit {}: git exited with {}.",
+                    commit,
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::InvalidCommit(commit) => {
+                write!(
+                    f,
+                    "Invalid commit {:?}: it can't start with a dash.",
+                    commit
+                )
+            }
+            ApplicationError::FailedSparseCheckoutCommand(err) => {
+                write!(f, "Failed to run the sparse checkout: {}", err)
+            }
+            ApplicationError::FailedSparseCheckout(status) => {
+                write!(
+                    f,
+                    "Failed to set up the sparse checkout: git exited with {}.",
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::FailedWorktreeCommand(err) => {
+                write!(f, "Failed to run the git worktree command: {}", err)
+            }
+            ApplicationError::FailedWorktree(branch, status) => {
+                write!(
+                    f,
+                    "Failed to add a worktree for branch {}: git exited with {}.",
+                    branch,
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::FailedPostCloneHookCommand(err) => {
+                write!(f, "Failed to run the post-clone hook: {}", err)
+            }
+            ApplicationError::FailedOpenEditorCommand(err) => {
+                write!(f, "Failed to open the editor: {}", err)
+            }
+            ApplicationError::EditorFailed(status) => {
+                write!(
+                    f,
+                    "The editor failed: it exited with {}.",
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::PostCloneHookFailed(status) => {
+                write!(
+                    f,
+                    "The post-clone hook failed: it exited with {}.",
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::FailedParsingRepo(err) => {
+                write!(f, "Failed to parse the repository URL: {}", err)
+            }
+            ApplicationError::ArgumentParsingError(err) => {
+                write!(f, "Failed to parse arguments: {}", err)
+            }
+            ApplicationError::InvalidDepth(depth) => {
+                write!(
+                    f,
+                    "Invalid clone depth {:?}: it must be a positive number, or 0 for the full history.",
+                    depth
+                )
+            }
+            ApplicationError::InvalidSince(since) => {
+                write!(f, "Invalid date {:?}: it can't be empty.", since)
+            }
+            ApplicationError::InvalidFilter(filter) => {
+                write!(
+                    f,
+                    "Invalid filter {:?}: it must be one of blob:none, blob:limit=<size>, tree:<depth>, object:type=<type>, sparse:oid=<blob> or combine:<filter>+<filter>.",
+                    filter
+                )
+            }
+            ApplicationError::InvalidBranch(branch, reason) => {
+                write!(f, "Invalid branch name {:?}: {}.", branch, reason)
+            }
+            ApplicationError::InvalidTimeout(timeout) => {
+                write!(
+                    f,
+                    "Invalid timeout {:?}: it must be a positive number of seconds.",
+                    timeout
+                )
+            }
+            ApplicationError::InvalidJobs(jobs) => {
+                write!(
+                    f,
+                    "Invalid number of jobs {:?}: it must be a positive number.",
+                    jobs
+                )
+            }
+            ApplicationError::InvalidRetries(retries) => {
+                write!(
+                    f,
+                    "Invalid number of retries {:?}: it must be zero or a positive number.",
+                    retries
+                )
+            }
+            ApplicationError::CloneTimedOut(timeout) => {
+                write!(
+                    f,
+                    "Failed to clone the repo: git took longer than {} seconds and was stopped.",
+                    timeout.as_secs()
+                )
+            }
+            ApplicationError::ConflictingOptions(first, second) => {
+                write!(
+                    f,
+                    "The {} and {} options can't be used together.",
+                    first, second
+                )
+            }
+            ApplicationError::UnsupportedShell(shell) => {
+                write!(
+                    f,
+                    "Unsupported shell {:?}: it must be one of bash, zsh or fish.",
+                    shell
+                )
+            }
+            ApplicationError::InvalidProtocol(protocol) => {
+                write!(
+                    f,
+                    "Invalid protocol {:?}: it must be one of \"ssh\", \"https\" or \"git\".",
+                    protocol
+                )
+            }
+            ApplicationError::InvalidLogLevel(level) => {
+                write!(
+                    f,
+                    "Invalid log level {:?}: it must be one of error, warn, info or debug.",
+                    level
+                )
+            }
+            ApplicationError::InvalidCloneArgs(args) => {
+                write!(
+                    f,
+                    "Invalid $GC_CLONE_ARGS {:?}: it has an unclosed quote or a trailing backslash.",
+                    args
+                )
+            }
+            ApplicationError::InvalidLayout(layout) => {
+                write!(
+                    f,
+                    "Invalid layout {:?}: it can only use the {{host}}, {{team}} and {{project}} placeholders.",
+                    layout
+                )
+            }
+            ApplicationError::LayoutVariableNotSet(layout, name) => {
+                write!(
+                    f,
+                    "The layout {:?} uses the ${} environment variable, but it's not set.",
+                    layout, name
+                )
+            }
+            ApplicationError::DestinationExists(path) => {
+                write!(
+                    f,
+                    "Destination directory {} already exists. Use --force to delete it and clone again.",
+                    path
+                )
+            }
+            ApplicationError::CantConfirmDeletion(path) => {
+                write!(
+                    f,
+                    "Destination directory {} already exists and there's no terminal to confirm deleting it. Use --force to delete it and clone again.",
+                    path
+                )
+            }
+            ApplicationError::Cancelled(path) => {
+                write!(f, "Cancelled, {} was left untouched.", path)
+            }
+            ApplicationError::NothingPicked => {
+                write!(f, "Cancelled, no repository was picked.")
+            }
+            ApplicationError::BranchNotFound(branch) => {
+                write!(
+                    f,
+                    "The branch {:?} does not exist in the remote repository.",
+                    branch
+                )
+            }
+            ApplicationError::TagNotFound(tag) => {
+                write!(
+                    f,
+                    "The tag {:?} does not exist in the remote repository.",
+                    tag
+                )
+            }
+            ApplicationError::TagBeforeSince(tag, since) => {
+                write!(
+                    f,
+                    "The tag {:?} is older than {:?}, so a clone with --since can't include it. Use --depth instead.",
+                    tag, since
+                )
+            }
+            ApplicationError::DefaultBranchNotFound(remote) => {
+                write!(
+                    f,
+                    "Cannot find the default branch of the {} remote.",
+                    remote
+                )
+            }
+            ApplicationError::FailedFetchDefaultBranchCommand(err) => {
+                write!(f, "Failed to run the git fetch command: {}", err)
+            }
+            ApplicationError::FailedFetchDefaultBranch(branch, status) => {
+                write!(
+                    f,
+                    "Failed to fetch the default branch {}: git exited with {}.",
+                    branch,
+                    describe_exit_status(status)
+                )
+            }
+            ApplicationError::OutputWithMultipleRepos => {
+                write!(
+                    f,
+                    "The --output option can only be used when cloning a single repository."
+                )
+            }
+            ApplicationError::OpenWithMultipleRepos => {
+                write!(
+                    f,
+                    "The --open option can only be used when cloning a single repository."
+                )
+            }
+            ApplicationError::StdinWithArguments => {
+                write!(
+                    f,
+                    "Repositories can't be given as arguments when reading them from --stdin."
+                )
+            }
+            ApplicationError::FailedRepositories(failed, total) => {
+                write!(
+                    f,
+                    "{} of {} repositories failed to clone, {} succeeded.",
+                    failed,
+                    total,
+                    total - failed
+                )
+            }
+        }
+    }
+}
+
+impl ApplicationError {
+    /// The exit code for the error: 2 when the user cancelled at a prompt,
+    /// so scripts can tell it apart from a failure, and 1 for everything
+    /// else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ApplicationError::Cancelled(_) | ApplicationError::NothingPicked => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl From<parser::ParseRepoError> for ApplicationError {
+    fn from(err: parser::ParseRepoError) -> Self {
+        ApplicationError::FailedParsingRepo(err)
+    }
+}
+
+impl std::error::Error for ApplicationError {}
@@ -0,0 +1,144 @@
+use std::env;
+
+/// Expands a leading `~` or `~user` to the home directory, and `$VAR` or
+/// `${VAR}` to the value of the environment variable, like a shell would.
+/// Anything that can't be expanded, like an unset variable, is left as is.
+pub fn expand_path(path: &str) -> String {
+    expand(path, |name| env::var(name).ok(), user_home)
+}
+
+fn expand(
+    path: &str,
+    var: impl Fn(&str) -> Option<String>,
+    user_home: impl Fn(&str) -> Option<String>,
+) -> String {
+    let path = expand_home(path, &var, user_home);
+    expand_vars(&path, var)
+}
+
+fn expand_home(
+    path: &str,
+    var: &impl Fn(&str) -> Option<String>,
+    user_home: impl Fn(&str) -> Option<String>,
+) -> String {
+    let Some(rest) = path.strip_prefix('~') else {
+        return path.to_string();
+    };
+
+    let (user, rest) = match rest.find(['/', '\\']) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+
+    let home = if user.is_empty() {
+        var("HOME").or_else(|| var("USERPROFILE"))
+    } else {
+        user_home(user)
+    };
+
+    match home.filter(|home| !home.is_empty()) {
+        Some(home) => format!("{}{}", home, rest),
+        None => path.to_string(),
+    }
+}
+
+// Replaces "$VAR" and "${VAR}" with what var returns for them, leaving them as
+// they are when it returns nothing.
+pub fn expand_vars(path: &str, mut var: impl FnMut(&str) -> Option<String>) -> String {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut expanded = String::new();
+    let mut rest = path;
+
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+
+        let (name, remainder, braced) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..], true),
+                None => ("", after, false),
+            },
+            None => {
+                let end = after.find(|c| !is_name(c)).unwrap_or(after.len());
+                (&after[..end], &after[end..], false)
+            }
+        };
+
+        let is_var = !name.is_empty() && name.chars().all(is_name);
+        match is_var.then(|| var(name)).flatten() {
+            Some(value) => expanded.push_str(&value),
+            None if braced => {
+                expanded.push_str("${");
+                expanded.push_str(name);
+                expanded.push('}');
+            }
+            None => {
+                expanded.push('$');
+                expanded.push_str(name);
+            }
+        }
+        rest = remainder;
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+// Looks the home directory of another user up in /etc/passwd, which is where
+// the shell gets it from too on most systems.
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() >= 6 && fields[0] == user).then(|| fields[5].to_string())
+    })
+}
+
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let var = |name: &str| match name {
+            "HOME" => Some("/home/me".to_string()),
+            "CODE" => Some("code".to_string()),
+            _ => None,
+        };
+        let user_home = |user: &str| match user {
+            "other" => Some("/home/other".to_string()),
+            _ => None,
+        };
+
+        let cases = vec![
+            ("~", "/home/me"),
+            ("~/code", "/home/me/code"),
+            ("~other/code", "/home/other/code"),
+            ("~nobody/code", "~nobody/code"),
+            ("/srv/code", "/srv/code"),
+            ("/srv/~/code", "/srv/~/code"),
+            ("$HOME/code", "/home/me/code"),
+            ("${HOME}/$CODE", "/home/me/code"),
+            ("~/${CODE}s", "/home/me/codes"),
+            ("$UNSET/code", "$UNSET/code"),
+            ("${UNSET}/code", "${UNSET}/code"),
+            ("/srv/$/code", "/srv/$/code"),
+            ("/srv/${CODE", "/srv/${CODE"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                expand(input, var, user_home),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use getopts::Options;
+
+// Every flag gc-rust takes. Both the parser and the completion scripts are
+// built from it, so a new flag only has to be added here.
+pub struct Flag {
+    pub short: &'static str,
+    pub long: &'static str,
+    pub description: &'static str,
+    pub kind: Kind,
+    // The values the flag takes, for the completion scripts to offer
+    pub values: &'static [&'static str],
+    // Left out of the completion scripts, for flags only they use
+    pub hidden: bool,
+}
+
+pub enum Kind {
+    Switch,
+    Value(&'static str),
+    // A value that's a file or directory, which completes to one
+    Path(&'static str),
+    Multi(&'static str),
+}
+
+pub const FLAGS: &[Flag] = &[
+    Flag {
+        short: "b",
+        long: "branch",
+        description: "set the branch to clone instead of the default branch",
+        kind: Kind::Value("BRANCH"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "tag",
+        description: "set the tag to check out instead of the default branch",
+        kind: Kind::Value("TAG"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "also-fetch-default-branch",
+        description: "also fetch the default branch when cloning another branch or a tag",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "worktree",
+        description: "check out BRANCH in a worktree next to the clone, cloning it first if needed",
+        kind: Kind::Value("BRANCH"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "commit",
+        description: "check out COMMIT once the repository is cloned",
+        kind: Kind::Value("COMMIT"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "depth",
+        description: "create a shallow clone with only the last DEPTH commits, 0 for the full history",
+        kind: Kind::Value("DEPTH"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "full",
+        description: "clone the full history, even if a default depth is configured",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "single-branch",
+        description: "only fetch the history of the branch being cloned, always the case with --depth",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "since",
+        description: "create a shallow clone with only the commits after DATE, like \"2 weeks ago\"",
+        kind: Kind::Value("DATE"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "filter",
+        description: "create a partial clone that skips the objects FILTER leaves out, like blob:none",
+        kind: Kind::Value("FILTER"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "base-subdir",
+        description: "set the subdirectory of the download path to clone into, \"src\" by default",
+        kind: Kind::Value("DIR"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "layout",
+        description: "set where repositories go inside the base directory, like {team}/{project}",
+        kind: Kind::Value("LAYOUT"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "prefix",
+        description: "put repositories in DIR inside the base directory, before the layout",
+        kind: Kind::Value("DIR"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "name",
+        description: "clone into a directory called NAME instead of the name of the project",
+        kind: Kind::Value("NAME"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "o",
+        long: "output",
+        description: "clone into DIR instead of the directory inside the base directory",
+        kind: Kind::Path("DIR"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "host",
+        description: "set the host assumed when the URL doesn't include one, github.com by default",
+        kind: Kind::Value("HOST"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "config-path",
+        description: "load the config from FILE instead of the default location",
+        kind: Kind::Path("FILE"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "profile",
+        description: "use the settings of the NAME profile from the config file",
+        kind: Kind::Value("NAME"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "git-binary",
+        description: "set the git binary to run, \"git\" from $PATH by default",
+        kind: Kind::Path("PATH"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "jobs",
+        description: "clone up to N repositories at once when cloning several",
+        kind: Kind::Value("N"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "timeout",
+        description: "stop git if it runs for longer than SECONDS",
+        kind: Kind::Value("SECONDS"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "retries",
+        description: "try cloning again up to N more times when git fails",
+        kind: Kind::Value("N"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "protocol",
+        description: "set the protocol used to clone: ssh (default), https or git",
+        kind: Kind::Value("PROTOCOL"),
+        values: &["ssh", "https", "git"],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "force-ssh",
+        description: "always clone over SSH, the same as --protocol ssh",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "force-https",
+        description: "always clone over HTTPS, the same as --protocol https",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "remote",
+        description: "set the name of the remote instead of \"origin\"",
+        kind: Kind::Value("NAME"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "post-clone",
+        description: "run COMMAND with the shell inside the repository once it's cloned",
+        kind: Kind::Value("COMMAND"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "bare",
+        description: "clone a bare repository, without a working tree, into a directory ending in .git",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "mirror",
+        description: "clone a mirror with every branch and tag, implies --bare",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "no-checkout",
+        description: "clone without checking anything out, leaving the working tree empty",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "sparse",
+        description: "only check out PATH, can be given more than once",
+        kind: Kind::Multi("PATH"),
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "recurse-submodules",
+        description: "clone the submodules of the repository too",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "shallow-submodules",
+        description: "clone the submodules with only their latest commit, implies --recurse-submodules",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "stdin",
+        description: "read the repositories to clone from stdin, one per line",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "no-prompt",
+        description: "never ask anything, failing instead when git needs credentials or the destination exists",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "n",
+        long: "dry-run",
+        description: "print what would be done without touching the disk or running git",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "f",
+        long: "force",
+        description: "delete the destination directory if it already exists",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "rename-existing",
+        description: "move an existing destination directory aside instead of deleting it",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "skip-existing",
+        description: "leave the destination alone if it's already a clone of the repository",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "u",
+        long: "update",
+        description: "pull the latest changes if the destination is already a git repository",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "plain",
+        description: "start status messages with ASCII markers like [ok] instead of icons",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "unshallow",
+        description: "fetch the full history of a repository cloned with --depth instead of cloning it",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "q",
+        long: "quiet",
+        description: "only print the destination directory and errors",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "quiet-git",
+        description: "hide the output of git, but keep the status messages",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "progress",
+        description: "show the progress of git as a single bar instead of its own output",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "v",
+        long: "verbose",
+        description: "print the git commands being run and their output when they fail",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "log-level",
+        description: "how much to print: error, warn, info (the default) or debug",
+        kind: Kind::Value("LEVEL"),
+        values: &["error", "warn", "info", "debug"],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "open",
+        description: "open the repository in $VISUAL or $EDITOR once it's cloned",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "parse",
+        description: "print the host, team and project of each repository without cloning it",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "json",
+        description: "print the result, or the error, as a JSON object to stdout",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "init",
+        description: "print a shell function that clones and then cds into the repository",
+        kind: Kind::Value("SHELL"),
+        values: &["bash", "zsh", "fish"],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "complete-repos",
+        description: "list the repositories already cloned, for the completion scripts",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: true,
+    },
+    Flag {
+        short: "",
+        long: "completions",
+        description: "print a completion script for SHELL with every flag",
+        kind: Kind::Value("SHELL"),
+        values: &["bash", "zsh", "fish"],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "where",
+        description: "print the directory the repository is, or would be, cloned into and exit",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "pick",
+        description: "pick one of the repositories already cloned and print its directory",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "list",
+        description: "list the repositories already cloned into the base directory",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+    Flag {
+        short: "",
+        long: "print-path-only",
+        description: "print nothing but the destination directory to stdout, for shell integrations",
+        kind: Kind::Switch,
+        values: &[],
+        hidden: false,
+    },
+];
+
+pub fn options() -> Options {
+    let mut opts = Options::new();
+    for flag in FLAGS {
+        match flag.kind {
+            Kind::Switch => opts.optflag(flag.short, flag.long, flag.description),
+            Kind::Value(hint) | Kind::Path(hint) => {
+                opts.optopt(flag.short, flag.long, flag.description, hint)
+            }
+            Kind::Multi(hint) => opts.optmulti(flag.short, flag.long, flag.description, hint),
+        };
+    }
+    opts
+}
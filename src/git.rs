@@ -0,0 +1,459 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+use std::{env, io};
+
+use subprocess::{CaptureData, Exec, ExitStatus, Popen, PopenError, Redirection};
+
+use crate::icon::Icon;
+use crate::output::Output;
+use crate::progress;
+
+// What asking the remote repository about a branch or tag found out.
+#[derive(Debug, PartialEq)]
+pub enum RemoteRef {
+    Found,
+    Missing,
+    // The remote couldn't be asked, like when it can't be reached
+    Unknown,
+}
+
+// Checks whether a branch or tag exists in the remote repository.
+pub fn remote_ref(output: &Output, clone_url: &str, reference: &str) -> RemoteRef {
+    let args = vec![
+        "ls-remote".to_string(),
+        "--exit-code".to_string(),
+        "--".to_string(),
+        clone_url.to_string(),
+        reference.to_string(),
+    ];
+
+    match run_git(output, &args, &env::temp_dir(), true) {
+        Ok(exec) => remote_ref_from_status(exec.exit_status),
+        Err(_) => RemoteRef::Unknown,
+    }
+}
+
+// ls-remote --exit-code exits with 0 when the reference is there and 2 when
+// it isn't, anything else is a failure to ask.
+fn remote_ref_from_status(status: ExitStatus) -> RemoteRef {
+    match status {
+        ExitStatus::Exited(0) => RemoteRef::Found,
+        ExitStatus::Exited(2) => RemoteRef::Missing,
+        _ => RemoteRef::Unknown,
+    }
+}
+
+// Finds the git binary to run, either a path to it or a name to look for in
+// $PATH, so a missing one is reported before anything is done.
+pub fn find_binary(binary: &str) -> Option<PathBuf> {
+    let path = Path::new(binary);
+    if path.components().count() > 1 {
+        return is_executable(path).then(|| path.to_path_buf());
+    }
+
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .flat_map(|dir| {
+            [
+                dir.join(binary),
+                dir.join(format!("{}{}", binary, env::consts::EXE_SUFFIX)),
+            ]
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+// Asks git for its version, which fails when it isn't installed or can't run.
+pub fn version(binary: &str) -> Option<String> {
+    let exec = Exec::cmd(binary)
+        .arg("--version")
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()
+        .ok()?;
+
+    exec.success().then(|| exec.stdout_str().trim().to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+pub fn is_git_repository(path: &Path) -> bool {
+    path.join(".git").exists()
+}
+
+// Reports whether the directory is a bare repository, like the ones cloned
+// with --bare or --mirror, which keep what's usually in .git at the top.
+pub fn is_bare_repository(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
+
+// Reports whether the directory is a git repository with changes that haven't
+// been committed. Directories that aren't git repositories have none.
+pub fn has_uncommitted_changes(output: &Output, path: &Path) -> bool {
+    let args = vec!["status".to_string(), "--porcelain".to_string()];
+
+    match run_git(output, &args, path, true) {
+        Ok(exec) => exec.success() && !exec.stdout_str().trim().is_empty(),
+        Err(_) => false,
+    }
+}
+
+// Returns the branch checked out in a repository, or nothing when HEAD is
+// detached or it can't be read.
+pub fn current_branch(output: &Output, path: &Path) -> Option<String> {
+    let args = vec![
+        "symbolic-ref".to_string(),
+        "--quiet".to_string(),
+        "--short".to_string(),
+        "HEAD".to_string(),
+    ];
+
+    match run_git(output, &args, path, true) {
+        Ok(exec) if exec.success() => Some(exec.stdout_str().trim().to_string()),
+        _ => None,
+    }
+}
+
+// Reads a path from the git config of the user, with "~" expanded by git. Not
+// having git installed is the same as not having the setting.
+pub fn config_path(binary: &str, key: &str) -> Option<String> {
+    let exec = Exec::cmd(binary)
+        .args(&["config", "--get", "--path", key])
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()
+        .ok()?;
+
+    let value = exec.stdout_str().trim().to_string();
+    (exec.success() && !value.is_empty()).then_some(value)
+}
+
+// Returns the URL of the remote of an existing clone, if it has one by that
+// name.
+pub fn remote_url(output: &Output, path: &Path, remote: &str) -> Option<String> {
+    let args = vec![
+        "remote".to_string(),
+        "get-url".to_string(),
+        "--".to_string(),
+        remote.to_string(),
+    ];
+
+    match run_git(output, &args, path, true) {
+        Ok(exec) if exec.success() => Some(exec.stdout_str().trim().to_string()),
+        _ => None,
+    }
+}
+
+// Asks the remote of an existing clone which branch its HEAD points to, which
+// is the branch a clone without --branch would have checked out.
+pub fn default_branch(output: &Output, path: &Path, remote: &str) -> Option<String> {
+    let args = vec![
+        "ls-remote".to_string(),
+        "--symref".to_string(),
+        "--".to_string(),
+        remote.to_string(),
+        "HEAD".to_string(),
+    ];
+
+    match run_git(output, &args, path, true) {
+        Ok(exec) if exec.success() => parse_symref(&exec.stdout_str()),
+        _ => None,
+    }
+}
+
+// Reads the branch out of the "ref: refs/heads/<branch>\tHEAD" line
+// ls-remote --symref prints before the commit HEAD points to.
+fn parse_symref(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (reference, name) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        let branch = reference.strip_prefix("refs/heads/")?;
+        (name == "HEAD" && !branch.is_empty()).then(|| branch.to_string())
+    })
+}
+
+// Reports whether the repository only has part of its history, like after a
+// clone with --depth.
+pub fn is_shallow_repository(output: &Output, path: &Path) -> bool {
+    let args = vec![
+        "rev-parse".to_string(),
+        "--is-shallow-repository".to_string(),
+    ];
+
+    match run_git(output, &args, path, true) {
+        Ok(exec) => exec.success() && exec.stdout_str().trim() == "true",
+        Err(_) => false,
+    }
+}
+
+// Reports whether the commit is present in the local repository.
+pub fn has_commit(output: &Output, path: &Path, commit: &str) -> bool {
+    let args = vec![
+        "cat-file".to_string(),
+        "-e".to_string(),
+        format!("{}^{{commit}}", commit),
+    ];
+
+    match run_git(output, &args, path, true) {
+        Ok(exec) => exec.success(),
+        Err(_) => false,
+    }
+}
+
+// Runs git with the given arguments from cwd. Its output goes wherever the
+// current output mode sends it, the buffer included, but never to stdout,
+// unless capture is set, in which case it's always collected. In verbose mode the command is echoed
+// before running and anything collected is shown if it fails. A command
+// running past the timeout is killed and reported as an error of the
+// io::ErrorKind::TimedOut kind.
+pub fn run_git(
+    output: &Output,
+    args: &[String],
+    cwd: &Path,
+    capture: bool,
+) -> Result<CaptureData, PopenError> {
+    output.verbose(format_args!(
+        "{} Running: git {} (in {})",
+        output.icon(Icon::Command),
+        format_command(args),
+        cwd.display()
+    ));
+
+    let progress = output.progress && !capture;
+    let (stdout, stderr) = if capture {
+        (Redirection::Pipe, Redirection::Pipe)
+    } else if progress {
+        // Everything is read as it comes to draw the progress bar
+        (Redirection::Merge, Redirection::Pipe)
+    } else {
+        (output.git_stdout(), output.git_stderr())
+    };
+
+    let mut exec = Exec::cmd(&output.git)
+        .args(args)
+        .cwd(cwd)
+        .stdout(stdout)
+        .stderr(stderr);
+    if output.no_prompt {
+        // Fail right away instead of waiting on a username or password
+        // nobody is going to type
+        exec = exec.env("GIT_TERMINAL_PROMPT", "0");
+    }
+
+    let started = Instant::now();
+    let mut process = exec.popen()?;
+    let renderer = match process.stderr.take() {
+        Some(stream) if progress => Some(thread::spawn(move || progress::render(stream))),
+        // Not drawing the bar, so put it back to be read below
+        stream => {
+            process.stderr = stream;
+            None
+        }
+    };
+
+    // Reading only waits for piped streams to close, so the process itself
+    // is waited on afterwards with whatever time is left
+    let mut communicator = process.communicate_start(None);
+    if let Some(timeout) = output.timeout {
+        communicator = communicator.limit_time(timeout);
+    }
+    let (captured_stdout, captured_stderr) = match communicator.read() {
+        Ok(captured) => captured,
+        Err(err) if err.error.kind() == io::ErrorKind::TimedOut => {
+            return Err(kill_timed_out(&mut process));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let exit_status = match output.timeout {
+        Some(timeout) => match process.wait_timeout(timeout.saturating_sub(started.elapsed()))? {
+            Some(status) => status,
+            None => return Err(kill_timed_out(&mut process)),
+        },
+        None => process.wait()?,
+    };
+    if let Some(renderer) = renderer {
+        let _ = renderer.join();
+    }
+    let exec = CaptureData {
+        stdout: captured_stdout.unwrap_or_default(),
+        stderr: captured_stderr.unwrap_or_default(),
+        exit_status,
+    };
+
+    // Collected to go out with the rest of the messages, like it would have
+    // been shown without a buffer
+    let buffered = !capture && !progress && output.buffers_git();
+    if buffered && !exec.stdout_str().trim().is_empty() {
+        output.report(format_args!("{}", exec.stdout_str().trim_end()));
+    }
+
+    if !exec.success() {
+        output.verbose(format_args!(
+            "{} git exited with {}",
+            output.icon(Icon::Warning),
+            describe_exit_status(&exec.exit_status)
+        ));
+        for captured in [exec.stdout_str(), exec.stderr_str()] {
+            // Already added to the buffer above
+            if !buffered && !captured.trim().is_empty() {
+                output.verbose(format_args!("{}", captured.trim_end()));
+            }
+        }
+    }
+
+    Ok(exec)
+}
+
+// Stops a git process that ran past its timeout and reports it as timed out.
+fn kill_timed_out(process: &mut Popen) -> PopenError {
+    // The process is gone either way, so failures here don't matter
+    let _ = process.kill();
+    let _ = process.wait();
+    PopenError::IoError(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "git took too long and was stopped",
+    ))
+}
+
+// Joins the arguments of a command so they can be shown to the user, quoting
+// the ones that would otherwise be ambiguous.
+pub fn format_command(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("{:?}", arg)
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn describe_exit_status(status: &ExitStatus) -> String {
+    match status {
+        ExitStatus::Exited(code) => format!("exit code {}", code),
+        ExitStatus::Signaled(signal) => format!("signal {}", signal),
+        ExitStatus::Other(code) => format!("status {}", code),
+        ExitStatus::Undetermined => "an unknown status".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::output::LogLevel;
+
+    #[test]
+    fn test_format_command() {
+        let cases = vec![
+            (
+                vec!["clone", "git@github.com:a/b.git"],
+                "clone git@github.com:a/b.git",
+            ),
+            (
+                vec!["clone", "/path with/spaces"],
+                "clone \"/path with/spaces\"",
+            ),
+            (vec!["checkout", ""], "checkout \"\""),
+        ];
+
+        for (args, expected) in cases {
+            let args: Vec<String> = args.into_iter().map(String::from).collect();
+            assert_eq!(format_command(&args), expected);
+        }
+    }
+
+    #[test]
+    fn test_remote_ref_from_status() {
+        let cases = vec![
+            (ExitStatus::Exited(0), RemoteRef::Found),
+            (ExitStatus::Exited(2), RemoteRef::Missing),
+            (ExitStatus::Exited(128), RemoteRef::Unknown),
+            (ExitStatus::Signaled(9), RemoteRef::Unknown),
+        ];
+
+        for (status, expected) in cases {
+            assert_eq!(remote_ref_from_status(status), expected);
+        }
+    }
+
+    #[test]
+    fn test_remote_ref_unreachable() {
+        let output = Output {
+            git: "git".to_string(),
+            level: LogLevel::Error,
+            quiet_git: true,
+            progress: false,
+            timeout: None,
+            no_prompt: true,
+            plain: true,
+            buffer: None,
+        };
+        let missing = env::temp_dir().join("gc-rust-test-missing-remote");
+
+        let state = remote_ref(&output, &missing.display().to_string(), "v1");
+        assert_eq!(state, RemoteRef::Unknown);
+    }
+
+    #[test]
+    fn test_run_git_buffered() {
+        let cases = vec![(false, true), (true, false)];
+
+        for (quiet_git, expected) in cases {
+            let mut output = Output {
+                git: "git".to_string(),
+                level: LogLevel::Info,
+                quiet_git,
+                progress: false,
+                timeout: None,
+                no_prompt: true,
+                plain: true,
+                buffer: Some(RefCell::new(String::new())),
+            };
+            let missing = env::temp_dir().join("gc-rust-test-missing-repo");
+            let args = vec!["-C".to_string(), missing.display().to_string()];
+
+            let exec = run_git(&output, &args, &env::temp_dir(), false).unwrap();
+            assert!(!exec.success());
+            // Taken out so it isn't printed when dropped
+            let buffer = output.buffer.take().unwrap().into_inner();
+            assert_eq!(buffer.contains("gc-rust-test-missing-repo"), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_symref() {
+        let cases = vec![
+            (
+                "ref: refs/heads/main\tHEAD\nac55393c5338fad43809bc0a3e8026302d5f8b22\tHEAD\n",
+                Some("main"),
+            ),
+            ("ref: refs/heads/release/1.x\tHEAD\n", Some("release/1.x")),
+            ("ac55393c5338fad43809bc0a3e8026302d5f8b22\tHEAD\n", None),
+            ("ref: refs/tags/v1.0\tHEAD\n", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_symref(input).as_deref(),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+}
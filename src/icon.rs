@@ -0,0 +1,42 @@
+/// The markers status messages start with. They're Nerd Font glyphs, or
+/// ASCII text when plain output is asked for, for terminals without the font
+/// and for log files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Icon {
+    Error,
+    Warning,
+    Success,
+    Clone,
+    Update,
+    CreateDir,
+    DirExists,
+    DryRun,
+    Command,
+    Hook,
+    Checkout,
+}
+
+impl Icon {
+    /// The marker to print, the glyph or its `plain` text.
+    pub fn text(self, plain: bool) -> &'static str {
+        let (glyph, text) = match self {
+            Icon::Error => ("\u{f071}", "[error]"),
+            Icon::Warning => ("\u{f071}", "[warn]"),
+            Icon::Success => ("\u{f058}", "[ok]"),
+            Icon::Clone => ("\u{ebcc}", "[clone]"),
+            Icon::Update => ("\u{f021}", "[update]"),
+            Icon::CreateDir => ("\u{ea83}", "[mkdir]"),
+            Icon::DirExists => ("\u{eb32}", "[exists]"),
+            Icon::DryRun => ("\u{f06e}", "[dry-run]"),
+            Icon::Command => ("\u{f120}", "[run]"),
+            Icon::Hook => ("\u{f0e7}", "[hook]"),
+            Icon::Checkout => ("\u{f126}", "[checkout]"),
+        };
+
+        if plain {
+            text
+        } else {
+            glyph
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+// A minimal JSON writer, just enough to print the results of gc in a
+// machine-readable way without pulling in a serialization framework.
+pub enum Value {
+    Null,
+    String(String),
+    Number(u64),
+}
+
+#[derive(Default)]
+pub struct Object {
+    fields: Vec<(String, Value)>,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.push((key.to_string(), value.into()));
+        self
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_char('{')?;
+        for (i, (key, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+            write_string(f, key)?;
+            write!(f, ":{}", value)?;
+        }
+        f.write_char('}')
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::String(value) => write_string(f, value),
+            Value::Number(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Self {
+        Value::String(value.clone())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object() {
+        let cases = vec![
+            (Object::new(), r#"{}"#),
+            (
+                Object::new().field("host", "github.com"),
+                r#"{"host":"github.com"}"#,
+            ),
+            (
+                Object::new()
+                    .field("team", "example")
+                    .field("branch", None::<String>)
+                    .field("project", Some("application")),
+                r#"{"team":"example","branch":null,"project":"application"}"#,
+            ),
+            (
+                Object::new()
+                    .field("duration_ms", 1234u64)
+                    .field("retries", None::<u64>),
+                r#"{"duration_ms":1234,"retries":null}"#,
+            ),
+        ];
+
+        for (object, expected) in cases {
+            assert_eq!(object.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_string_escaping() {
+        let cases = vec![
+            ("plain", r#""plain""#),
+            ("with \"quotes\"", r#""with \"quotes\"""#),
+            (r"C:\path", r#""C:\\path""#),
+            ("line\nbreak\ttab", r#""line\nbreak\ttab""#),
+            ("\u{1}", r#""\u0001""#),
+            ("\u{f058} glyph", "\"\u{f058} glyph\""),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(Value::from(input).to_string(), expected);
+        }
+    }
+}
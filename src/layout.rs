@@ -0,0 +1,122 @@
+use std::env;
+
+use crate::error::ApplicationError;
+use crate::expand::expand_vars;
+use crate::parser::Repository;
+
+pub const DEFAULT_LAYOUT: &str = "{host}/{team}/{project}";
+
+// Renders a layout template like "{host}/{team}/{project}" into the path of
+// the repository relative to the base directory. Environment variables in it,
+// like "$USER", are expanded first.
+pub fn render(layout: &str, repo: &Repository) -> Result<String, ApplicationError> {
+    let expanded = expand_env(layout, |name| env::var(name).ok())?;
+    let invalid = || ApplicationError::InvalidLayout(layout.to_string());
+    let mut rendered = String::new();
+    let mut rest = expanded.as_str();
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(invalid)? + start;
+
+        match &rest[start + 1..end] {
+            "host" => rendered.push_str(&repo.host),
+            "team" => rendered.push_str(&repo.team),
+            "project" => rendered.push_str(&repo.project),
+            _ => return Err(invalid()),
+        }
+
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    let rendered = rendered.trim_matches('/');
+    if rendered.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(rendered.to_string())
+}
+
+// An unset or empty variable is an error rather than an empty folder name, so
+// repositories never silently end up somewhere else.
+fn expand_env(
+    layout: &str,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<String, ApplicationError> {
+    let mut missing = None;
+    let expanded = expand_vars(layout, |name| {
+        let value = var(name).filter(|value| !value.is_empty());
+        if value.is_none() && missing.is_none() {
+            missing = Some(name.to_string());
+        }
+        value
+    });
+
+    match missing {
+        Some(name) => Err(ApplicationError::LayoutVariableNotSet(
+            layout.to_string(),
+            name,
+        )),
+        None => Ok(expanded),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let repo = Repository {
+            host: "github.com".to_string(),
+            port: None,
+            protocol: None,
+            team: "example".to_string(),
+            project: "application".to_string(),
+        };
+
+        let cases = vec![
+            (DEFAULT_LAYOUT, Some("github.com/example/application")),
+            ("{team}/{project}", Some("example/application")),
+            ("{project}", Some("application")),
+            (
+                "code/{host}/{team}-{project}",
+                Some("code/github.com/example-application"),
+            ),
+            ("/{team}/{project}/", Some("example/application")),
+            ("{owner}/{project}", None),
+            ("{team}/{project", None),
+            ("", None),
+        ];
+
+        for (layout, expected) in cases {
+            let rendered = render(layout, &repo).ok();
+            assert_eq!(rendered.as_deref(), expected);
+        }
+    }
+
+    #[test]
+    fn test_expand_env() {
+        let var = |name: &str| match name {
+            "USER" => Some("patrick".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+
+        let cases = vec![
+            (DEFAULT_LAYOUT, Some(DEFAULT_LAYOUT)),
+            ("$USER/{host}/{project}", Some("patrick/{host}/{project}")),
+            ("{host}/${USER}-{team}", Some("{host}/patrick-{team}")),
+            ("{host}/$/{project}", Some("{host}/$/{project}")),
+            ("$UNSET/{project}", None),
+            ("${UNSET}/{project}", None),
+            ("$EMPTY/{project}", None),
+        ];
+
+        for (layout, expected) in cases {
+            let expanded = expand_env(layout, var).ok();
+            assert_eq!(expanded.as_deref(), expected, "layout: {}", layout);
+        }
+    }
+}
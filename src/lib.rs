@@ -0,0 +1,2733 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal};
+use std::path::{self, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use std::{env, fs};
+
+use subprocess::{CaptureData, Exec, PopenError, Redirection};
+
+mod color;
+mod error;
+mod expand;
+mod git;
+mod icon;
+mod layout;
+mod list;
+mod output;
+mod parser;
+mod progress;
+
+pub use color::{paint, Color};
+pub use error::ApplicationError;
+pub use expand::expand_path;
+pub use icon::Icon;
+pub use layout::DEFAULT_LAYOUT;
+pub use list::cloned_repos;
+pub use output::LogLevel;
+pub use parser::{
+    expand_alias, repository, repository_with_host, strip_credentials, ParseRepoError, Protocol,
+    Repository, DEFAULT_HOST,
+};
+
+use git::{
+    config_path, current_branch, default_branch, find_binary, format_command, has_commit,
+    has_uncommitted_changes, is_git_repository, is_shallow_repository, remote_ref, remote_url,
+    run_git, RemoteRef,
+};
+use output::Output;
+
+/// Everything needed to clone a repository into the directory layout used by
+/// gc. Use [`CloneOptions::new`] to start from the defaults.
+#[derive(Debug, Clone)]
+pub struct CloneOptions {
+    /// The repository to clone, in any of the forms gc accepts.
+    pub repo_url: String,
+    /// The directory repositories are cloned into.
+    pub base_dir: String,
+    /// The host assumed when the URL doesn't include one, like in
+    /// `team/project`.
+    pub default_host: String,
+    /// Short names for hosts, so `gh:team/project` can stand for
+    /// `github.com/team/project` when `gh` is aliased to `github.com`.
+    pub host_aliases: BTreeMap<String, String>,
+    /// Where the repository goes inside the base directory, using the
+    /// `{host}`, `{team}` and `{project}` placeholders.
+    pub layout: String,
+    /// Directories to put between the base directory and the layout, like
+    /// `work/backend`, checked with [`parse_prefix`].
+    pub prefix: Option<String>,
+    /// Clone into this directory instead, ignoring the base directory and the
+    /// layout. Relative paths are resolved from the current directory.
+    pub output_dir: Option<String>,
+    /// The name of the directory to clone into, instead of the name of the
+    /// project. It's only used in the layout, the clone URL is unchanged.
+    pub name: Option<String>,
+    /// The branch to clone instead of the default branch.
+    pub branch: Option<String>,
+    /// The tag to check out instead of the default branch. Can't be combined
+    /// with `branch`.
+    pub tag: Option<String>,
+    /// The commit to check out once the repository is cloned. If a shallow
+    /// clone doesn't include it, the rest of the history is fetched.
+    pub commit: Option<String>,
+    /// Create a shallow clone with only this many commits.
+    pub depth: Option<u32>,
+    /// Create a shallow clone with only the commits after this date, in any
+    /// form git understands, like `2024-01-31` or `2 weeks ago`.
+    pub shallow_since: Option<String>,
+    /// Only fetch the history of the branch being cloned, the default one or
+    /// `branch`. Always the case with `depth`.
+    pub single_branch: bool,
+    /// A partial clone filter, like `blob:none` or `tree:0`, to skip
+    /// downloading objects until they're needed.
+    pub filter: Option<String>,
+    /// The name to give the remote instead of git's default, `origin`.
+    pub remote: Option<String>,
+    /// How long each git command may run before it's stopped. No limit when
+    /// unset.
+    pub timeout: Option<Duration>,
+    /// Show the progress of git as a single bar instead of its own output.
+    /// Ignored when `quiet` is set or stderr isn't a terminal.
+    pub progress: bool,
+    /// The git binary to run, either a path to it or a name to look for in
+    /// `$PATH`.
+    pub git_binary: String,
+    /// How many more times to try cloning when git fails.
+    pub retries: u32,
+    /// Never ask anything: git fails instead of asking for credentials, and
+    /// an existing destination is an error unless `force` is set. git never
+    /// asks for credentials when stdin or stderr isn't a terminal either.
+    pub no_prompt: bool,
+    /// A shell command to run inside the repository once it's cloned, with
+    /// its path in `$GC_REPO_PATH`.
+    pub post_clone: Option<String>,
+    /// Instead of cloning, fetch the full history of a repository that was
+    /// cloned with `depth`.
+    pub unshallow: bool,
+    /// Clone a bare repository, without a working tree, into a directory
+    /// ending in `.git`.
+    pub bare: bool,
+    /// Clone a mirror of the repository, with every reference in it. Implies
+    /// `bare`.
+    pub mirror: bool,
+    /// Clone the submodules of the repository too.
+    pub recurse_submodules: bool,
+    /// Clone the submodules with only their latest commit. Implies
+    /// `recurse_submodules`.
+    pub shallow_submodules: bool,
+    /// Clone without checking anything out, leaving the working tree empty.
+    /// `branch` is still the branch that's checked out later.
+    pub no_checkout: bool,
+    /// Only check out these directories, with git's sparse checkout.
+    pub sparse: Vec<String>,
+    /// Also fetch the tip of the default branch of the remote into a local
+    /// branch, when cloning another branch or a tag. Only its latest commit
+    /// is fetched when the clone is shallow.
+    pub also_fetch_default_branch: bool,
+    /// Check this branch out in a worktree next to the clone, in a directory
+    /// named `project@branch`, cloning the repository first if needed.
+    pub worktree: Option<String>,
+    /// Extra arguments given to `git clone` as they are, after the ones
+    /// computed from these options.
+    pub git_args: Vec<String>,
+    /// The protocol to clone with. When unset, the protocol of the URL is
+    /// used, or `default_protocol` if the URL doesn't specify one.
+    pub protocol: Option<Protocol>,
+    /// The protocol to clone with when neither `protocol` nor the URL says
+    /// which one, SSH by default.
+    pub default_protocol: Protocol,
+    /// Delete the destination directory without asking if it already exists.
+    pub force: bool,
+    /// Move an existing destination directory aside, to one ending in
+    /// `.bak.` and the time, instead of deleting it.
+    pub rename_existing: bool,
+    /// Pull the latest changes if the destination is already a repository.
+    pub update: bool,
+    /// Leave the destination as it is if it's already a clone of the same
+    /// repository.
+    pub skip_existing: bool,
+    /// Print what would be done without touching the disk or running git.
+    pub dry_run: bool,
+    /// Hide the status messages and the output of git.
+    pub quiet: bool,
+    /// Print the git commands being run and their output when they fail.
+    pub verbose: bool,
+    /// Hide the output of git while still printing the status messages.
+    pub quiet_git: bool,
+    /// Collect the status messages of the clone and print them together once
+    /// it's done, for clones running at the same time. The output of git is
+    /// hidden and there's no progress bar.
+    pub buffer_output: bool,
+    /// How much to print, overriding `quiet` and `verbose` when set.
+    pub log_level: Option<LogLevel>,
+    /// Start status messages with ASCII markers instead of Nerd Font glyphs.
+    pub plain: bool,
+}
+
+impl CloneOptions {
+    pub fn new(repo_url: &str, base_dir: &str) -> Self {
+        CloneOptions {
+            repo_url: repo_url.to_string(),
+            base_dir: base_dir.to_string(),
+            default_host: DEFAULT_HOST.to_string(),
+            host_aliases: BTreeMap::new(),
+            layout: DEFAULT_LAYOUT.to_string(),
+            prefix: None,
+            output_dir: None,
+            name: None,
+            branch: None,
+            tag: None,
+            commit: None,
+            depth: None,
+            shallow_since: None,
+            single_branch: false,
+            filter: None,
+            remote: None,
+            timeout: None,
+            progress: false,
+            git_binary: "git".to_string(),
+            retries: 0,
+            no_prompt: false,
+            post_clone: None,
+            unshallow: false,
+            bare: false,
+            mirror: false,
+            recurse_submodules: false,
+            shallow_submodules: false,
+            no_checkout: false,
+            sparse: Vec::new(),
+            also_fetch_default_branch: false,
+            worktree: None,
+            git_args: Vec::new(),
+            protocol: None,
+            default_protocol: Protocol::Ssh,
+            force: false,
+            rename_existing: false,
+            update: false,
+            skip_existing: false,
+            dry_run: false,
+            quiet: false,
+            verbose: false,
+            quiet_git: false,
+            buffer_output: false,
+            log_level: None,
+            plain: false,
+        }
+    }
+
+    /// The level status messages are printed at: `log_level` when set, or
+    /// else the one `quiet` or `verbose` stand for.
+    pub fn level(&self) -> LogLevel {
+        match self.log_level {
+            Some(level) => level,
+            None if self.quiet => LogLevel::Error,
+            None if self.verbose => LogLevel::Debug,
+            None => LogLevel::Info,
+        }
+    }
+}
+
+/// What was done with the destination of a repository.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Cloned,
+    /// Already a clone, pulled or unshallowed in place.
+    Updated,
+    /// Already a clone, left as it was.
+    Skipped,
+}
+
+/// Where a repository ended up and how it was cloned.
+pub struct CloneOutcome {
+    pub host: String,
+    pub team: String,
+    pub project: String,
+    pub path: String,
+    pub clone_url: String,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub commit: Option<String>,
+    /// How long git took to clone the repository, including any retries.
+    /// Not set when nothing was cloned, like when updating in place.
+    pub duration: Option<Duration>,
+    pub action: Action,
+}
+
+/// The subdirectory of the download path repositories are cloned into, kept
+/// from the days when all code lived in `$GOPATH/src`.
+pub const DEFAULT_BASE_SUBDIR: &str = "src";
+
+/// Finds the directory repositories are cloned into from the
+/// `$GC_DOWNLOAD_PATH` or `$GOPATH` environment variables, or else the
+/// `gc.downloadPath` git setting, read with `git_binary`, inside the given
+/// subdirectory. An empty subdirectory uses the download path itself. When
+/// none of them is set, `$HOME/git` is used and created if needed.
+pub fn default_base_dir(subdir: &str, git_binary: &str) -> Result<String, ApplicationError> {
+    let download_path = env::var("GC_DOWNLOAD_PATH")
+        .or_else(|_| env::var("GOPATH"))
+        .ok()
+        .or_else(|| config_path(git_binary, "gc.downloadPath"));
+    let (mut base_dir, is_fallback) = match download_path {
+        Some(path) => (PathBuf::from(expand_path(&path)), false),
+        None => (Path::new(&home_dir()?).join("git"), true),
+    };
+
+    // Pushed one component at a time, so the separators are the right ones
+    // for the platform
+    base_dir.extend(subdir.split('/').filter(|component| !component.is_empty()));
+    let base_dir = base_dir.display().to_string();
+
+    if is_fallback && !Path::new(&base_dir).exists() {
+        fs::create_dir_all(&base_dir)
+            .map_err(|err| ApplicationError::CantCreateBaseDir(base_dir.clone(), err))?;
+    }
+
+    Ok(base_dir)
+}
+
+/// Resolves the directory repositories are cloned into, either the configured
+/// one, with `~` and environment variables expanded, or the default one inside
+/// the given subdirectory, and makes sure it can be opened. It's meant to be
+/// called once, with every clone of the run sharing the result.
+pub fn resolve_base_dir(
+    configured: Option<&str>,
+    subdir: &str,
+    git_binary: &str,
+) -> Result<PathBuf, ApplicationError> {
+    let base_dir = match configured {
+        Some(base_dir) => PathBuf::from(expand_path(base_dir)),
+        None => PathBuf::from(default_base_dir(subdir, git_binary)?),
+    };
+
+    fs::read_dir(&base_dir).map_err(ApplicationError::BaseDirCannotBeOpened)?;
+    Ok(base_dir)
+}
+
+/// Makes sure files can be created in the base directory, so a read-only one
+/// is reported as such rather than as a failure to create the destination.
+/// It's meant to be called once before cloning, like [`resolve_base_dir`].
+pub fn check_writable(base_dir: &str) -> Result<(), ApplicationError> {
+    let probe = Path::new(base_dir).join(format!(".gc-rust-write-test-{}", std::process::id()));
+    fs::File::create(&probe)
+        .map_err(|err| ApplicationError::BaseDirNotWritable(base_dir.to_string(), err))?;
+    // It's fine if it lingers, it's empty and hidden
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+fn home_dir() -> Result<String, ApplicationError> {
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .ok()
+        .filter(|home| !home.is_empty())
+        .ok_or(ApplicationError::BaseDirNotFound)
+}
+
+/// Finds the directory the repository would be cloned into, without checking
+/// whether it, or the base directory, exists.
+pub fn repo_path(options: &CloneOptions) -> Result<String, ApplicationError> {
+    let repo = parse_repository(options)?;
+    destination(options, &repo)
+}
+
+fn parse_repository(options: &CloneOptions) -> Result<parser::Repository, ApplicationError> {
+    let repo_url = parser::expand_alias(&options.repo_url, &options.host_aliases);
+    Ok(parser::repository_with_host(
+        repo_url,
+        &options.default_host,
+    )?)
+}
+
+fn destination(
+    options: &CloneOptions,
+    repo: &parser::Repository,
+) -> Result<String, ApplicationError> {
+    // git runs from the parent directory, so the path has to be absolute
+    let project_path = match &options.output_dir {
+        Some(output_dir) => path::absolute(output_dir)
+            .map_err(|err| ApplicationError::InvalidOutputDir(output_dir.clone(), err))?,
+        None => {
+            let rendered = match &options.name {
+                Some(name) => layout::render(
+                    &options.layout,
+                    &parser::Repository {
+                        project: name.clone(),
+                        ..repo.clone()
+                    },
+                )?,
+                None => layout::render(&options.layout, repo)?,
+            };
+            // Bare repositories are named like on servers, so they aren't
+            // mistaken for regular ones
+            let rendered = if options.bare || options.mirror {
+                format!("{}.git", rendered)
+            } else {
+                rendered
+            };
+            let mut project_path = PathBuf::from(&options.base_dir);
+            if let Some(prefix) = &options.prefix {
+                project_path.extend(prefix.split('/'));
+            }
+            project_path.extend(rendered.split('/'));
+            path::absolute(project_path).map_err(ApplicationError::BaseDirCannotBeOpened)?
+        }
+    };
+
+    Ok(project_path.display().to_string())
+}
+
+// Runs git clone, trying again with an increasing delay as many times as
+// requested when git fails, since that's often a flaky network. Failing to
+// run git at all isn't retried.
+fn run_clone(
+    output: &Output,
+    options: &CloneOptions,
+    clone_args: &[String],
+    project_path: &str,
+) -> Result<CaptureData, ApplicationError> {
+    let mut attempt = 0;
+    loop {
+        let exec =
+            run_git(output, clone_args, clone_dir(project_path), false).map_err(
+                |err| match err {
+                    PopenError::IoError(err) if err.kind() == io::ErrorKind::TimedOut => {
+                        ApplicationError::CloneTimedOut(options.timeout.unwrap_or_default())
+                    }
+                    err => ApplicationError::FailedCloneCommand(err),
+                },
+            )?;
+
+        if exec.success() || attempt == options.retries {
+            return Ok(exec);
+        }
+
+        attempt += 1;
+        let delay = Duration::from_secs(1 << (attempt - 1).min(6));
+        output.warning(format_args!(
+            "{} Clone failed, retrying in {}s...",
+            output.icon(Icon::Warning),
+            delay.as_secs()
+        ));
+        output.verbose(format_args!(
+            "{} Retry {} of {}",
+            output.icon(Icon::Update),
+            attempt,
+            options.retries
+        ));
+        thread::sleep(delay);
+
+        // git only clones into empty directories
+        if Path::new(project_path).exists() {
+            fs::remove_dir_all(project_path).map_err(ApplicationError::CantDeleteTargetDir)?;
+        }
+    }
+}
+
+// Checks whether the destination is already a clone of the repository, going
+// by the URL of its remote.
+fn is_existing_clone(output: &Output, options: &CloneOptions, outcome: &CloneOutcome) -> bool {
+    let path = Path::new(&outcome.path);
+    let remote = options.remote.as_deref().unwrap_or("origin");
+    is_git_repository(path)
+        && remote_url(output, path, remote)
+            .is_some_and(|url| same_repository(&url, &outcome.clone_url, &options.default_host))
+}
+
+// Pulls the latest changes into an existing clone, as long as that doesn't
+// need a merge.
+fn update(output: &Output, outcome: &CloneOutcome) -> Result<(), ApplicationError> {
+    output.status(format_args!(
+        "{} Updating {}/{}...",
+        output.icon(Icon::Update),
+        outcome.team,
+        outcome.project
+    ));
+
+    let args = vec!["pull".to_string(), "--ff-only".to_string()];
+    let exec = run_git(output, &args, Path::new(&outcome.path), false)
+        .map_err(ApplicationError::FailedUpdateCommand)?;
+
+    if !exec.success() {
+        return Err(ApplicationError::FailedUpdate(exec.exit_status));
+    }
+
+    output.success(format_args!(
+        "{} Successfully updated {}/{} in {}",
+        output.icon(Icon::Success),
+        outcome.team,
+        outcome.project,
+        outcome.path
+    ));
+
+    Ok(())
+}
+
+// Compares what the URLs point to rather than the URLs themselves, so a clone
+// made over HTTPS still matches when cloning over SSH.
+fn same_repository(url: &str, clone_url: &str, default_host: &str) -> bool {
+    let (Ok(first), Ok(second)) = (
+        repository_with_host(url.to_string(), default_host),
+        repository_with_host(clone_url.to_string(), default_host),
+    ) else {
+        return false;
+    };
+
+    first.host.eq_ignore_ascii_case(&second.host)
+        && first.team.eq_ignore_ascii_case(&second.team)
+        && first.project.eq_ignore_ascii_case(&second.project)
+}
+
+// Fetches the rest of the history of a repository cloned with --depth.
+fn unshallow(output: &Output, path: &Path) -> Result<(), ApplicationError> {
+    if !is_git_repository(path) {
+        return Err(ApplicationError::NotAGitRepository(
+            path.display().to_string(),
+        ));
+    }
+
+    if !is_shallow_repository(output, path) {
+        return Err(ApplicationError::NotShallow(path.display().to_string()));
+    }
+
+    output.status(format_args!(
+        "{} Fetching the full history into {}...",
+        output.icon(Icon::Update),
+        path.display()
+    ));
+
+    let args = vec!["fetch".to_string(), "--unshallow".to_string()];
+    let exec =
+        run_git(output, &args, path, false).map_err(ApplicationError::FailedUnshallowCommand)?;
+    if !exec.success() {
+        return Err(ApplicationError::FailedUnshallow(exec.exit_status));
+    }
+
+    Ok(())
+}
+
+// Removes what a failed clone left behind, like when git was stopped before
+// cleaning up after itself, so the next attempt doesn't find an existing
+// destination. Failing to do so isn't worth hiding the clone error over.
+fn remove_failed_clone(output: &Output, project_path: &str) {
+    if !Path::new(project_path).exists() {
+        return;
+    }
+
+    if let Err(err) = fs::remove_dir_all(project_path) {
+        output.verbose(format_args!(
+            "{} Could not remove {} after the failed clone: {}",
+            output.icon(Icon::Warning),
+            project_path,
+            err
+        ));
+    }
+}
+
+// The clone runs from the directory the destination is created in, which is
+// created beforehand, so git picks up any configuration that applies to it.
+fn clone_dir(project_path: &str) -> &Path {
+    Path::new(project_path)
+        .parent()
+        .unwrap_or(Path::new(project_path))
+}
+
+fn check_conflicts(options: &CloneOptions) -> Result<(), ApplicationError> {
+    let conflicts = [
+        (
+            "--branch",
+            options.branch.is_some(),
+            "--tag",
+            options.tag.is_some(),
+        ),
+        // The output directory already has a name of its own
+        (
+            "--output",
+            options.output_dir.is_some(),
+            "--name",
+            options.name.is_some(),
+        ),
+        ("--bare", options.bare, "--branch", options.branch.is_some()),
+        // There's no working tree to check out into
+        ("--bare", options.bare, "--commit", options.commit.is_some()),
+        (
+            "--bare",
+            options.bare,
+            "--sparse",
+            !options.sparse.is_empty(),
+        ),
+        // Both check something out once the clone is done
+        (
+            "--no-checkout",
+            options.no_checkout,
+            "--commit",
+            options.commit.is_some(),
+        ),
+        (
+            "--no-checkout",
+            options.no_checkout,
+            "--sparse",
+            !options.sparse.is_empty(),
+        ),
+        // Unshallowing works on an existing clone, it doesn't make a new one
+        ("--unshallow", options.unshallow, "--update", options.update),
+        (
+            "--unshallow",
+            options.unshallow,
+            "--depth",
+            options.depth.is_some(),
+        ),
+        (
+            "--unshallow",
+            options.unshallow,
+            "--since",
+            options.shallow_since.is_some(),
+        ),
+        // Both decide how much history to fetch
+        (
+            "--depth",
+            options.depth.is_some(),
+            "--since",
+            options.shallow_since.is_some(),
+        ),
+        // A mirror has every branch and tag, so there's no picking one
+        (
+            "--mirror",
+            options.mirror,
+            "--branch",
+            options.branch.is_some(),
+        ),
+        ("--mirror", options.mirror, "--tag", options.tag.is_some()),
+        (
+            "--mirror",
+            options.mirror,
+            "--single-branch",
+            options.single_branch,
+        ),
+        (
+            "--mirror",
+            options.mirror,
+            "--commit",
+            options.commit.is_some(),
+        ),
+        (
+            "--mirror",
+            options.mirror,
+            "--sparse",
+            !options.sparse.is_empty(),
+        ),
+        // A mirror already has every branch
+        (
+            "--also-fetch-default-branch",
+            options.also_fetch_default_branch,
+            "--mirror",
+            options.mirror,
+        ),
+        // A worktree needs a working tree to start from, and the branch it
+        // checks out has to be part of the clone
+        (
+            "--worktree",
+            options.worktree.is_some(),
+            "--bare",
+            options.bare,
+        ),
+        (
+            "--worktree",
+            options.worktree.is_some(),
+            "--mirror",
+            options.mirror,
+        ),
+        (
+            "--worktree",
+            options.worktree.is_some(),
+            "--depth",
+            options.depth.is_some(),
+        ),
+        (
+            "--worktree",
+            options.worktree.is_some(),
+            "--single-branch",
+            options.single_branch,
+        ),
+        (
+            "--worktree",
+            options.worktree.is_some(),
+            "--unshallow",
+            options.unshallow,
+        ),
+        (
+            "--worktree",
+            options.worktree.is_some(),
+            "--update",
+            options.update,
+        ),
+        // Both decide what happens to an existing clone
+        (
+            "--skip-existing",
+            options.skip_existing,
+            "--update",
+            options.update,
+        ),
+        // Both decide what happens to an existing destination
+        (
+            "--rename-existing",
+            options.rename_existing,
+            "--force",
+            options.force,
+        ),
+    ];
+
+    for (first, first_set, second, second_set) in conflicts {
+        if first_set && second_set {
+            return Err(ApplicationError::ConflictingOptions(
+                first.to_string(),
+                second.to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the git binary can be found and run, so a missing one is
+/// reported before anything else is done. The default `git` not being in
+/// `$PATH` gets its own error, since it's the most common setup problem.
+pub fn check_git(binary: &str) -> Result<(), ApplicationError> {
+    if find_binary(binary).is_some() && git::version(binary).is_some() {
+        return Ok(());
+    }
+
+    if binary == "git" {
+        Err(ApplicationError::GitNotFound)
+    } else {
+        Err(ApplicationError::GitBinaryNotFound(binary.to_string()))
+    }
+}
+
+/// Clones a repository into the base directory following the layout, or
+/// updates it in place when requested. If the destination already exists and
+/// `force` isn't set, the user is asked on stdin before it's deleted. The base
+/// directory is expected to exist already, like the one `resolve_base_dir`
+/// returns, writable as [`check_writable`] checks, and git to have been found
+/// with [`check_git`].
+pub fn clone_repo(options: CloneOptions) -> Result<CloneOutcome, ApplicationError> {
+    // Drawing the bar only makes sense when someone's looking at it
+    let options = CloneOptions {
+        progress: options.progress
+            && !options.buffer_output
+            && options.level() >= LogLevel::Info
+            && io::stderr().is_terminal(),
+        ..options
+    };
+    let output = Output {
+        git: options.git_binary.clone(),
+        level: options.level(),
+        quiet_git: options.quiet_git,
+        progress: options.progress,
+        timeout: options.timeout,
+        // Without a terminal to answer in, git would wait on credentials
+        // forever
+        no_prompt: options.no_prompt || !io::stdin().is_terminal() || !io::stderr().is_terminal(),
+        plain: options.plain,
+        buffer: options.buffer_output.then(|| RefCell::new(String::new())),
+    };
+
+    check_conflicts(&options)?;
+
+    // git checkout would take a commit that looks like a flag for one
+    if let Some(commit) = options.commit.as_ref().filter(|c| c.starts_with('-')) {
+        return Err(ApplicationError::InvalidCommit(commit.clone()));
+    }
+
+    // Parse the repository URL
+    let repo = parse_repository(&options)?;
+    let project_path = destination(&options, &repo)?;
+    let clone_url = clone_url(&repo, clone_protocol(&options, &repo));
+
+    let clone_args = clone_args(&options, &clone_url, &project_path);
+
+    let outcome = CloneOutcome {
+        host: repo.host,
+        team: repo.team,
+        project: repo.project,
+        path: project_path,
+        clone_url,
+        branch: options.branch.clone(),
+        tag: options.tag.clone(),
+        commit: options.commit.clone(),
+        duration: None,
+        action: Action::Cloned,
+    };
+    let CloneOutcome {
+        team,
+        project,
+        path: project_path,
+        clone_url,
+        ..
+    } = &outcome;
+
+    if options.dry_run {
+        output.report(format_args!(
+            "{} Dry run for {}/{}, nothing will be changed.",
+            output.icon(Icon::DryRun),
+            team,
+            project
+        ));
+        output.report(format_args!("Destination directory: {}", project_path));
+        if options.unshallow {
+            output.report(format_args!("Unshallow command: git fetch --unshallow"));
+            return Ok(outcome);
+        }
+        if options.update && is_git_repository(Path::new(project_path)) {
+            output.report(format_args!(
+                "Destination directory is a git repository and would be updated."
+            ));
+            output.report(format_args!("Update command: git pull --ff-only"));
+        } else if options.skip_existing && is_existing_clone(&output, &options, &outcome) {
+            output.report(format_args!(
+                "Destination directory is already a clone and would be skipped."
+            ));
+            return Ok(outcome);
+        } else if Path::new(project_path).exists() {
+            if options.rename_existing {
+                output.report(format_args!(
+                    "Destination directory already exists and would be moved to {}.",
+                    backup_path(project_path, SystemTime::now())
+                ));
+            } else if options.force {
+                output.report(format_args!(
+                    "Destination directory already exists and would be deleted."
+                ));
+            } else {
+                output.report(format_args!("Destination directory already exists and would only be deleted after confirmation."));
+            }
+        }
+        output.report(format_args!("Clone URL: {}", clone_url));
+        output.report(format_args!(
+            "Clone command: git {}",
+            format_command(&clone_args)
+        ));
+        if !options.sparse.is_empty() {
+            output.report(format_args!(
+                "Sparse checkout command: git {}",
+                format_command(&sparse_checkout_args(&options.sparse))
+            ));
+        }
+        if let Some(commit) = &options.commit {
+            output.report(format_args!("Checkout command: git checkout {} --", commit));
+        } else if !options.sparse.is_empty() {
+            output.report(format_args!("Checkout command: git checkout"));
+        }
+        if options.also_fetch_default_branch {
+            output.report(format_args!(
+                "Default branch command: git {}",
+                format_command(&fetch_branch_args(&options, "<default>"))
+            ));
+        }
+        if let Some(command) = &options.post_clone {
+            output.report(format_args!("Post-clone hook: {}", command));
+        }
+        if let Some(branch) = &options.worktree {
+            let path = worktree_path(project_path, branch);
+            output.report(format_args!("Worktree directory: {}", path));
+            output.report(format_args!(
+                "Worktree command: git {}",
+                format_command(&worktree_args(&path, branch))
+            ));
+            return Ok(CloneOutcome {
+                path,
+                branch: Some(branch.clone()),
+                ..outcome
+            });
+        }
+
+        return Ok(outcome);
+    }
+
+    if options.unshallow {
+        unshallow(&output, Path::new(project_path))?;
+        output.success(format_args!(
+            "{} Successfully fetched the full history of {}/{} in {}",
+            output.icon(Icon::Success),
+            team,
+            project,
+            project_path
+        ));
+        return Ok(CloneOutcome {
+            action: Action::Updated,
+            ..outcome
+        });
+    }
+
+    // Update the repository in place instead of cloning it again
+    if options.update && is_git_repository(Path::new(project_path)) {
+        update(&output, &outcome)?;
+        return Ok(CloneOutcome {
+            action: Action::Updated,
+            ..outcome
+        });
+    }
+
+    // A clone that's already there only needs the new worktree
+    if let Some(branch) = &options.worktree {
+        if is_git_repository(Path::new(project_path)) {
+            return add_worktree(&output, outcome, branch);
+        }
+    }
+
+    if options.skip_existing && is_existing_clone(&output, &options, &outcome) {
+        output.warning(format_args!(
+            "{} {}/{} is already cloned in {}, skipping.",
+            output.icon(Icon::Warning),
+            team,
+            project,
+            project_path
+        ));
+        return Ok(CloneOutcome {
+            action: Action::Skipped,
+            ..outcome
+        });
+    }
+
+    // Only the parent directory is created, git creates the destination
+    // itself and removes it again if the clone fails
+    if !Path::new(project_path).exists() {
+        output.status(format_args!(
+            "{} Destination directory for {}/{} does not exist. Creating...",
+            output.icon(Icon::CreateDir),
+            team,
+            project
+        ));
+        fs::create_dir_all(clone_dir(project_path))
+            .map_err(ApplicationError::CantCreateTargetDir)?;
+    } else if options.rename_existing {
+        let backup = backup_path(project_path, SystemTime::now());
+        output.status(format_args!(
+            "{} Destination directory for {}/{} already exists. Moving it to {}...",
+            output.icon(Icon::DirExists),
+            team,
+            project,
+            backup
+        ));
+        fs::rename(project_path, &backup).map_err(ApplicationError::CantRenameTargetDir)?;
+    } else {
+        output.status(format_args!(
+            "{} Destination directory for {}/{} already exists.",
+            output.icon(Icon::DirExists),
+            team,
+            project
+        ));
+
+        // Tell a clone of this same repository apart from anything else
+        let remote = options.remote.as_deref().unwrap_or("origin");
+        let is_repo = is_git_repository(Path::new(project_path));
+        let same_repo = match is_repo
+            .then(|| remote_url(&output, Path::new(project_path), remote))
+            .flatten()
+        {
+            Some(url) if same_repository(&url, clone_url, &options.default_host) => true,
+            Some(url) => {
+                output.warning(format_args!(
+                    "{} Warning: {} is a clone of {}, not of {}/{}.",
+                    output.icon(Icon::Warning),
+                    project_path,
+                    strip_credentials(&url),
+                    team,
+                    project
+                ));
+                false
+            }
+            None => false,
+        };
+
+        // Without a question to show them in, uncommitted changes get their
+        // own warning
+        let dirty = has_uncommitted_changes(&output, Path::new(project_path));
+        if dirty && options.force {
+            output.warning(format_args!(
+                "{} Warning: {} has uncommitted changes that will be lost.",
+                output.icon(Icon::Warning),
+                project_path
+            ));
+        }
+
+        if !options.force && options.no_prompt {
+            return Err(ApplicationError::DestinationExists(project_path.clone()));
+        }
+
+        // Without a terminal nobody can answer, and a closed or piped stdin
+        // must never be taken as a yes
+        if !options.force && !io::stdin().is_terminal() {
+            return Err(ApplicationError::CantConfirmDeletion(project_path.clone()));
+        }
+
+        if !options.force {
+            let branch = is_repo
+                .then(|| current_branch(&output, Path::new(project_path)))
+                .flatten();
+            eprintln!(
+                "{}",
+                paint(
+                    Color::Yellow,
+                    format_args!(
+                        "{} {} will be deleted: {}.",
+                        output.icon(Icon::Warning),
+                        project_path,
+                        describe_destination(is_repo, branch.as_deref(), dirty)
+                    )
+                )
+            );
+
+            let question = if same_repo {
+                "Type \"update\" to pull the latest changes instead, \"yes\" to delete it and clone again, or anything else to cancel:"
+            } else {
+                "Type \"yes\" to delete it and clone again, or anything else to cancel:"
+            };
+            eprintln!("{}", paint(Color::Yellow, question));
+            let mut input = String::new();
+            io::stdin()
+                .read_line(&mut input)
+                .map_err(ApplicationError::FailedCaptureInput)?;
+
+            match input.trim() {
+                "yes" => {}
+                "update" if same_repo => {
+                    update(&output, &outcome)?;
+                    return Ok(CloneOutcome {
+                        action: Action::Updated,
+                        ..outcome
+                    });
+                }
+                _ => return Err(ApplicationError::Cancelled(project_path.clone())),
+            }
+        }
+
+        fs::remove_dir_all(project_path).map_err(ApplicationError::CantDeleteTargetDir)?;
+    }
+
+    // Run the git clone command
+    match (&options.branch, &options.tag) {
+        (Some(branch), _) => output.status(format_args!(
+            "{} Cloning {}/{} at branch {}...",
+            output.icon(Icon::Clone),
+            team,
+            project,
+            branch
+        )),
+        (_, Some(tag)) => output.status(format_args!(
+            "{} Cloning {}/{} at tag {}...",
+            output.icon(Icon::Clone),
+            team,
+            project,
+            tag
+        )),
+        _ => output.status(format_args!(
+            "{} Cloning {}/{}...",
+            output.icon(Icon::Clone),
+            team,
+            project
+        )),
+    }
+
+    // The destination didn't exist or was deleted above, so whatever is in
+    // it now came from this clone and is safe to remove if it fails
+    let started = Instant::now();
+    let exec = match run_clone(&output, &options, &clone_args, project_path) {
+        Ok(exec) => exec,
+        Err(err) => {
+            remove_failed_clone(&output, project_path);
+            return Err(err);
+        }
+    };
+
+    if !exec.success() {
+        remove_failed_clone(&output, project_path);
+
+        // Tell a missing branch apart from any other clone failure, since git
+        // would otherwise only report it amid the rest of its output.
+        if let Some(branch) = &options.branch {
+            if remote_ref(&output, clone_url, branch) == RemoteRef::Missing {
+                return Err(ApplicationError::BranchNotFound(branch.clone()));
+            }
+        }
+
+        if let Some(tag) = &options.tag {
+            let found = match remote_ref(&output, clone_url, tag) {
+                RemoteRef::Missing => return Err(ApplicationError::TagNotFound(tag.clone())),
+                state => state == RemoteRef::Found,
+            };
+
+            // The tag is there, so what failed is reaching it: git can't
+            // cut the history at a date that's later than the tag
+            if let (true, Some(since)) = (found, &options.shallow_since) {
+                return Err(ApplicationError::TagBeforeSince(tag.clone(), since.clone()));
+            }
+        }
+
+        return Err(ApplicationError::FailedGitOperation(exec.exit_status));
+    }
+
+    // The clone was made without a checkout, so pick the directories before
+    // checking anything out
+    if !options.sparse.is_empty() {
+        sparse_checkout(
+            &output,
+            Path::new(project_path),
+            &options.sparse,
+            options.commit.is_none(),
+        )?;
+    }
+
+    if let Some(commit) = &options.commit {
+        checkout_commit(&output, Path::new(project_path), commit)?;
+    }
+
+    if options.also_fetch_default_branch {
+        fetch_default_branch(&output, &options, Path::new(project_path))?;
+    }
+
+    let duration = started.elapsed();
+    output.success(format_args!(
+        "{} Successfully cloned {}/{} into {} in {}",
+        output.icon(Icon::Success),
+        team,
+        project,
+        project_path,
+        format_duration(duration)
+    ));
+
+    if let Some(command) = &options.post_clone {
+        run_post_clone_hook(&output, command, Path::new(project_path))?;
+    }
+
+    let outcome = CloneOutcome {
+        duration: Some(duration),
+        ..outcome
+    };
+    if let Some(branch) = &options.worktree {
+        return add_worktree(&output, outcome, branch);
+    }
+
+    Ok(outcome)
+}
+
+// Fetches the default branch of the remote into a local branch of the same
+// name, unless it's the branch that was just cloned.
+fn fetch_default_branch(
+    output: &Output,
+    options: &CloneOptions,
+    path: &Path,
+) -> Result<(), ApplicationError> {
+    // Without a branch or a tag, the default branch is the one that was cloned
+    if options.branch.is_none() && options.tag.is_none() {
+        return Ok(());
+    }
+
+    let remote = options.remote.as_deref().unwrap_or("origin");
+    let branch = default_branch(output, path, remote)
+        .ok_or(ApplicationError::DefaultBranchNotFound(remote.to_string()))?;
+    if options.branch.as_ref() == Some(&branch) {
+        return Ok(());
+    }
+
+    output.status(format_args!(
+        "{} Fetching the default branch, {}...",
+        output.icon(Icon::Update),
+        branch
+    ));
+
+    let args = fetch_branch_args(options, &branch);
+    let exec = run_git(output, &args, path, false)
+        .map_err(ApplicationError::FailedFetchDefaultBranchCommand)?;
+    if !exec.success() {
+        return Err(ApplicationError::FailedFetchDefaultBranch(
+            branch,
+            exec.exit_status,
+        ));
+    }
+
+    Ok(())
+}
+
+// A shallow clone stays shallow: fetching the whole history of another
+// branch would undo the point of it.
+fn fetch_branch_args(options: &CloneOptions, branch: &str) -> Vec<String> {
+    let mut args = vec!["fetch".to_string()];
+    if options.depth.is_some() || options.shallow_since.is_some() {
+        args.push("--depth".to_string());
+        args.push("1".to_string());
+    }
+
+    args.push("--".to_string());
+    args.push(options.remote.as_deref().unwrap_or("origin").to_string());
+    args.push(format!("{}:{}", branch, branch));
+    args
+}
+
+// Describes what's in a destination that's about to be deleted, so nobody
+// confirms without knowing what they'd lose.
+fn describe_destination(is_repo: bool, branch: Option<&str>, dirty: bool) -> String {
+    if !is_repo {
+        return "it's not a git repository".to_string();
+    }
+
+    let head = match branch {
+        Some(branch) => format!("on branch {}", branch),
+        None => "with a detached HEAD".to_string(),
+    };
+    let changes = if dirty {
+        "uncommitted changes"
+    } else {
+        "no uncommitted changes"
+    };
+    format!("it's a git repository {}, with {}", head, changes)
+}
+
+// Where an existing destination is moved to, next to it and named after the
+// time in UTC, like "project.bak.20240101T120000".
+fn backup_path(project_path: &str, now: SystemTime) -> String {
+    let secs = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, time) = (secs / 86_400, secs % 86_400);
+
+    // Howard Hinnant's days_from_civil, run backwards
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{}.bak.{:04}{:02}{:02}T{:02}{:02}{:02}",
+        project_path.trim_end_matches(['/', path::MAIN_SEPARATOR]),
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+// Tenths of a second are enough for a clone, and minutes are easier to read
+// than hundreds of seconds.
+fn format_duration(duration: Duration) -> String {
+    let tenths = duration.as_millis() / 100;
+    if tenths < 600 {
+        return format!("{}.{}s", tenths / 10, tenths % 10);
+    }
+
+    let seconds = tenths / 10;
+    format!("{}m {:02}s", seconds / 60, seconds % 60)
+}
+
+// Checks the branch out in a directory next to the clone, and reports that
+// directory as where the repository ended up. An existing worktree is left
+// as it is, so running the same command twice lands in the same place.
+fn add_worktree(
+    output: &Output,
+    outcome: CloneOutcome,
+    branch: &str,
+) -> Result<CloneOutcome, ApplicationError> {
+    let path = worktree_path(&outcome.path, branch);
+
+    if Path::new(&path).exists() {
+        output.status(format_args!(
+            "{} Worktree for branch {} already exists.",
+            output.icon(Icon::DirExists),
+            branch
+        ));
+    } else {
+        output.status(format_args!(
+            "{} Adding a worktree for branch {}...",
+            output.icon(Icon::Checkout),
+            branch
+        ));
+
+        let args = worktree_args(&path, branch);
+        let exec = run_git(output, &args, Path::new(&outcome.path), false)
+            .map_err(ApplicationError::FailedWorktreeCommand)?;
+        if !exec.success() {
+            return Err(ApplicationError::FailedWorktree(
+                branch.to_string(),
+                exec.exit_status,
+            ));
+        }
+
+        output.success(format_args!(
+            "{} Successfully checked out {} into {}",
+            output.icon(Icon::Success),
+            branch,
+            path
+        ));
+    }
+
+    Ok(CloneOutcome {
+        path,
+        branch: Some(branch.to_string()),
+        ..outcome
+    })
+}
+
+// Slashes in the branch name would nest the worktree in other directories,
+// so they become dashes.
+fn worktree_path(project_path: &str, branch: &str) -> String {
+    format!("{}@{}", project_path, branch.replace('/', "-"))
+}
+
+// git creates a local branch tracking the remote one when only the remote
+// has it.
+fn worktree_args(path: &str, branch: &str) -> Vec<String> {
+    vec![
+        "worktree".to_string(),
+        "add".to_string(),
+        path.to_string(),
+        branch.to_string(),
+    ]
+}
+
+// Runs the hook through the shell from inside the new clone. Like git, its
+// output goes to stderr so stdout is left for the path.
+fn run_post_clone_hook(
+    output: &Output,
+    command: &str,
+    path: &Path,
+) -> Result<(), ApplicationError> {
+    output.status(format_args!(
+        "{} Running post-clone hook: {}",
+        output.icon(Icon::Hook),
+        command
+    ));
+
+    let shell = if cfg!(windows) {
+        Exec::cmd("cmd").arg("/C")
+    } else {
+        Exec::cmd("sh").arg("-c")
+    };
+    let shell = shell.arg(command).cwd(path).env("GC_REPO_PATH", path);
+    let status = if output.is_buffered() {
+        // Kept with the rest of the messages of the clone
+        let exec = shell
+            .stdout(Redirection::Pipe)
+            .stderr(Redirection::Merge)
+            .capture()
+            .map_err(ApplicationError::FailedPostCloneHookCommand)?;
+        let text = exec.stdout_str();
+        if !text.trim().is_empty() {
+            output.report(format_args!("{}", text.trim_end()));
+        }
+        exec.exit_status
+    } else {
+        shell
+            .stdout(Redirection::Merge)
+            .join()
+            .map_err(ApplicationError::FailedPostCloneHookCommand)?
+    };
+
+    if !status.success() {
+        return Err(ApplicationError::PostCloneHookFailed(status));
+    }
+
+    Ok(())
+}
+
+// Limits the working tree to the given directories, then checks out the
+// branch that was cloned unless a commit is checked out afterwards instead.
+fn sparse_checkout(
+    output: &Output,
+    path: &Path,
+    directories: &[String],
+    checkout: bool,
+) -> Result<(), ApplicationError> {
+    output.status(format_args!(
+        "{} Setting up a sparse checkout of {}...",
+        output.icon(Icon::Checkout),
+        directories.join(", ")
+    ));
+
+    let args = sparse_checkout_args(directories);
+    let exec = run_git(output, &args, path, false)
+        .map_err(ApplicationError::FailedSparseCheckoutCommand)?;
+    if !exec.success() {
+        return Err(ApplicationError::FailedSparseCheckout(exec.exit_status));
+    }
+
+    if !checkout {
+        return Ok(());
+    }
+
+    let args = vec!["checkout".to_string()];
+    let exec = run_git(output, &args, path, false)
+        .map_err(ApplicationError::FailedSparseCheckoutCommand)?;
+    if !exec.success() {
+        return Err(ApplicationError::FailedSparseCheckout(exec.exit_status));
+    }
+
+    Ok(())
+}
+
+fn sparse_checkout_args(directories: &[String]) -> Vec<String> {
+    let mut args = vec!["sparse-checkout".to_string(), "set".to_string()];
+    args.extend(directories.iter().cloned());
+    args
+}
+
+// Checks out a commit of a freshly cloned repository, fetching the rest of
+// the history first when the clone is shallow and doesn't include it.
+fn checkout_commit(output: &Output, path: &Path, commit: &str) -> Result<(), ApplicationError> {
+    if is_shallow_repository(output, path) && !has_commit(output, path, commit) {
+        output.warning(format_args!(
+            "{} Commit {} is not part of the shallow clone, fetching the full history...",
+            output.icon(Icon::Warning),
+            commit
+        ));
+
+        let args = vec!["fetch".to_string(), "--unshallow".to_string()];
+        let exec =
+            run_git(output, &args, path, false).map_err(ApplicationError::FailedCheckoutCommand)?;
+        if !exec.success() {
+            return Err(ApplicationError::FailedCheckout(
+                commit.to_string(),
+                exec.exit_status,
+            ));
+        }
+    }
+
+    output.status(format_args!(
+        "{} Checking out commit {}...",
+        output.icon(Icon::Checkout),
+        commit
+    ));
+
+    // The "--" makes sure the commit is never taken for a path
+    let args = vec!["checkout".to_string(), commit.to_string(), "--".to_string()];
+    let exec =
+        run_git(output, &args, path, false).map_err(ApplicationError::FailedCheckoutCommand)?;
+    if !exec.success() {
+        return Err(ApplicationError::FailedCheckout(
+            commit.to_string(),
+            exec.exit_status,
+        ));
+    }
+
+    Ok(())
+}
+
+// Clones using the same protocol the URL was given with, unless asked
+// otherwise, since that's the one the user is known to have access with.
+fn clone_protocol(options: &CloneOptions, repo: &parser::Repository) -> Protocol {
+    options
+        .protocol
+        .or(repo.protocol)
+        .unwrap_or(options.default_protocol)
+}
+
+fn clone_url(repo: &parser::Repository, protocol: Protocol) -> String {
+    // The port is dropped when cloning with another protocol than the one it
+    // was given for
+    let https_port = repo.port.filter(|_| repo.protocol == Some(Protocol::Https));
+    let git_port = repo.port.filter(|_| repo.protocol == Some(Protocol::Git));
+    let ssh_port = repo
+        .port
+        .filter(|_| !matches!(repo.protocol, Some(Protocol::Https | Protocol::Git)));
+
+    if parser::is_gist(&repo.host) {
+        return match protocol {
+            Protocol::Https => format!("https://{}/{}.git", repo.host, repo.project),
+            Protocol::Ssh => format!("git@{}:{}.git", repo.host, repo.project),
+            Protocol::Git => format!("git://{}/{}.git", repo.host, repo.project),
+        };
+    }
+
+    let suffix = if parser::is_sourcehut(&repo.team) {
+        ""
+    } else {
+        ".git"
+    };
+    match (protocol, https_port, ssh_port) {
+        (Protocol::Https, Some(port), _) => format!(
+            "https://{}:{}/{}/{}{}",
+            repo.host, port, repo.team, repo.project, suffix
+        ),
+        (Protocol::Https, None, _) => format!(
+            "https://{}/{}/{}{}",
+            repo.host, repo.team, repo.project, suffix
+        ),
+        (Protocol::Ssh, _, Some(port)) => format!(
+            "ssh://git@{}:{}/{}/{}{}",
+            repo.host, port, repo.team, repo.project, suffix
+        ),
+        (Protocol::Ssh, _, None) => {
+            format!("git@{}:{}/{}{}", repo.host, repo.team, repo.project, suffix)
+        }
+        (Protocol::Git, _, _) => match git_port {
+            Some(port) => format!(
+                "git://{}:{}/{}/{}{}",
+                repo.host, port, repo.team, repo.project, suffix
+            ),
+            None => format!(
+                "git://{}/{}/{}{}",
+                repo.host, repo.team, repo.project, suffix
+            ),
+        },
+    }
+}
+
+pub fn parse_protocol(protocol: &str) -> Result<Protocol, ApplicationError> {
+    match protocol.trim().to_lowercase().as_str() {
+        "ssh" => Ok(Protocol::Ssh),
+        "https" => Ok(Protocol::Https),
+        "git" => Ok(Protocol::Git),
+        _ => Err(ApplicationError::InvalidProtocol(protocol.to_string())),
+    }
+}
+
+pub fn parse_log_level(level: &str) -> Result<LogLevel, ApplicationError> {
+    match level.trim().to_lowercase().as_str() {
+        "error" => Ok(LogLevel::Error),
+        "warn" => Ok(LogLevel::Warn),
+        "info" => Ok(LogLevel::Info),
+        "debug" => Ok(LogLevel::Debug),
+        _ => Err(ApplicationError::InvalidLogLevel(level.to_string())),
+    }
+}
+
+pub fn parse_depth(depth: &str) -> Result<u32, ApplicationError> {
+    match depth.trim().parse::<u32>() {
+        Ok(depth) if depth > 0 => Ok(depth),
+        _ => Err(ApplicationError::InvalidDepth(depth.to_string())),
+    }
+}
+
+pub fn parse_branch(branch: &str) -> Result<String, ApplicationError> {
+    let invalid = |reason: &str| {
+        Err(ApplicationError::InvalidBranch(
+            branch.to_string(),
+            reason.to_string(),
+        ))
+    };
+
+    // A subset of the rules of git check-ref-format, enough to catch typos
+    // and names git would take for one of its flags
+    if branch.is_empty() {
+        return invalid("it can't be empty");
+    }
+    if branch.starts_with('-') {
+        return invalid("it can't start with a dash");
+    }
+    if branch.chars().any(|c| c.is_control()) {
+        return invalid("it can't contain control characters");
+    }
+    if branch.contains(' ') {
+        return invalid("it can't contain spaces");
+    }
+    if branch.contains(['~', '^', ':', '?', '*', '[', '\\']) {
+        return invalid("it can't contain any of ~ ^ : ? * [ \\");
+    }
+    if branch.contains("..") || branch.contains("@{") {
+        return invalid("it can't contain \"..\" or \"@{\"");
+    }
+    if branch.starts_with('/') || branch.ends_with('/') || branch.ends_with('.') {
+        return invalid("it can't start or end with a slash, or end with a dot");
+    }
+
+    Ok(branch.to_string())
+}
+
+pub fn parse_name(name: &str) -> Result<String, ApplicationError> {
+    // It's a single directory, anything that would go somewhere else isn't
+    match name {
+        "" | "." | ".." => Err(ApplicationError::InvalidName(name.to_string())),
+        _ if name.contains(['/', '\\']) => Err(ApplicationError::InvalidName(name.to_string())),
+        _ => Ok(name.to_string()),
+    }
+}
+
+pub fn parse_prefix(prefix: &str) -> Result<String, ApplicationError> {
+    // Empty and "." segments don't go anywhere, but ".." would leave the base
+    // directory
+    let segments: Vec<&str> = prefix
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+    if segments.is_empty() || segments.contains(&"..") {
+        return Err(ApplicationError::InvalidPrefix(prefix.to_string()));
+    }
+
+    Ok(segments.join("/"))
+}
+
+pub fn parse_since(since: &str) -> Result<String, ApplicationError> {
+    // git handles the many forms of dates itself, and fails the clone with
+    // its own error when it can't make sense of one
+    match since.trim() {
+        "" => Err(ApplicationError::InvalidSince(since.to_string())),
+        since => Ok(since.to_string()),
+    }
+}
+
+pub fn parse_filter(filter: &str) -> Result<String, ApplicationError> {
+    let filter = filter.trim();
+    let valid = match filter.strip_prefix("combine:") {
+        Some(filters) => filters.split('+').all(is_filter_spec),
+        None => is_filter_spec(filter),
+    };
+
+    if valid {
+        Ok(filter.to_string())
+    } else {
+        Err(ApplicationError::InvalidFilter(filter.to_string()))
+    }
+}
+
+// Checks the filter against the shapes git accepts for --filter, so a typo
+// is caught before starting a clone that git would reject anyway.
+fn is_filter_spec(filter: &str) -> bool {
+    let is_number = |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+
+    if filter == "blob:none" {
+        return true;
+    }
+
+    if let Some(limit) = filter.strip_prefix("blob:limit=") {
+        let limit = limit
+            .strip_suffix(['k', 'm', 'g', 'K', 'M', 'G'])
+            .unwrap_or(limit);
+        return is_number(limit);
+    }
+
+    if let Some(depth) = filter.strip_prefix("tree:") {
+        return is_number(depth);
+    }
+
+    if let Some(kind) = filter.strip_prefix("object:type=") {
+        return matches!(kind, "blob" | "tree" | "commit" | "tag");
+    }
+
+    if let Some(oid) = filter.strip_prefix("sparse:oid=") {
+        return !oid.is_empty();
+    }
+
+    false
+}
+
+pub fn parse_retries(retries: &str) -> Result<u32, ApplicationError> {
+    retries
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| ApplicationError::InvalidRetries(retries.to_string()))
+}
+
+pub fn parse_jobs(jobs: &str) -> Result<usize, ApplicationError> {
+    match jobs.trim().parse::<usize>() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => Err(ApplicationError::InvalidJobs(jobs.to_string())),
+    }
+}
+
+pub fn parse_timeout(timeout: &str) -> Result<Duration, ApplicationError> {
+    match timeout.trim().parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+        _ => Err(ApplicationError::InvalidTimeout(timeout.to_string())),
+    }
+}
+
+fn clone_args(options: &CloneOptions, clone_url: &str, project_path: &str) -> Vec<String> {
+    let mut args = vec!["clone".to_string()];
+
+    // git only reports progress to terminals unless asked to
+    if options.progress {
+        args.push("--progress".to_string());
+    }
+
+    // git accepts tags for --branch too, leaving the clone at a detached HEAD.
+    // Unlike checking the tag out afterwards, it's fetched along with the
+    // branch even when the clone is shallow and only has a single branch.
+    if let Some(reference) = options.branch.as_ref().or(options.tag.as_ref()) {
+        args.push("--branch".to_string());
+        args.push(reference.to_string());
+    }
+
+    if let Some(remote) = &options.remote {
+        args.push("--origin".to_string());
+        args.push(remote.to_string());
+    }
+
+    if let Some(depth) = options.depth {
+        args.push("--depth".to_string());
+        args.push(depth.to_string());
+    }
+
+    // A shallow clone only makes sense for the branch being cloned, so avoid
+    // fetching the tips of every other branch too.
+    if options.single_branch || options.depth.is_some() {
+        args.push("--single-branch".to_string());
+    }
+
+    if let Some(since) = &options.shallow_since {
+        args.push(format!("--shallow-since={}", since));
+    }
+
+    if let Some(filter) = &options.filter {
+        args.push(format!("--filter={}", filter));
+    }
+
+    // The sparse checkout is set up before anything is checked out
+    if options.no_checkout || !options.sparse.is_empty() {
+        args.push("--no-checkout".to_string());
+    }
+
+    // git's --mirror already implies --bare
+    if options.mirror {
+        args.push("--mirror".to_string());
+    } else if options.bare {
+        args.push("--bare".to_string());
+    }
+
+    if options.recurse_submodules || options.shallow_submodules {
+        args.push("--recurse-submodules".to_string());
+    }
+
+    if options.shallow_submodules {
+        args.push("--shallow-submodules".to_string());
+    }
+
+    args.extend(options.git_args.iter().cloned());
+    // Nothing past this point can be taken for a flag, whatever it starts with
+    args.push("--".to_string());
+    args.push(clone_url.to_string());
+    args.push(project_path.to_string());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(edit: impl FnOnce(&mut CloneOptions)) -> CloneOptions {
+        let mut options = CloneOptions::new("team/project", "/src");
+        edit(&mut options);
+        options
+    }
+
+    #[test]
+    fn test_clone_args() {
+        let cases = vec![
+            (
+                options(|_| {}),
+                vec![
+                    "clone",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.branch = Some("feature".to_string())),
+                vec![
+                    "clone",
+                    "--branch",
+                    "feature",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.branch = Some("--upload-pack=touch".to_string())),
+                vec![
+                    "clone",
+                    "--branch",
+                    "--upload-pack=touch",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.tag = Some("v1.0.0".to_string())),
+                vec![
+                    "clone",
+                    "--branch",
+                    "v1.0.0",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.depth = Some(1)),
+                vec![
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--single-branch",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.depth = Some(1);
+                    o.tag = Some("v1.0.0".to_string());
+                }),
+                vec![
+                    "clone",
+                    "--branch",
+                    "v1.0.0",
+                    "--depth",
+                    "1",
+                    "--single-branch",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.shallow_since = Some("2024-01-31".to_string());
+                    o.tag = Some("v1.0.0".to_string());
+                }),
+                vec![
+                    "clone",
+                    "--branch",
+                    "v1.0.0",
+                    "--shallow-since=2024-01-31",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.depth = Some(1);
+                    o.git_args = vec!["--filter=blob:none".to_string(), "--sparse".to_string()];
+                }),
+                vec![
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--single-branch",
+                    "--filter=blob:none",
+                    "--sparse",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.depth = Some(1);
+                    o.filter = Some("blob:none".to_string());
+                }),
+                vec![
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--single-branch",
+                    "--filter=blob:none",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.branch = Some("feature".to_string());
+                    o.no_checkout = true;
+                }),
+                vec![
+                    "clone",
+                    "--branch",
+                    "feature",
+                    "--no-checkout",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.sparse = vec!["docs".to_string()]),
+                vec![
+                    "clone",
+                    "--no-checkout",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.single_branch = true),
+                vec![
+                    "clone",
+                    "--single-branch",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.branch = Some("feature".to_string());
+                    o.depth = Some(1);
+                    o.single_branch = true;
+                }),
+                vec![
+                    "clone",
+                    "--branch",
+                    "feature",
+                    "--depth",
+                    "1",
+                    "--single-branch",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.shallow_since = Some("2 weeks ago".to_string())),
+                vec![
+                    "clone",
+                    "--shallow-since=2 weeks ago",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.remote = Some("upstream".to_string())),
+                vec![
+                    "clone",
+                    "--origin",
+                    "upstream",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.bare = true),
+                vec![
+                    "clone",
+                    "--bare",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.bare = true;
+                    o.mirror = true;
+                }),
+                vec![
+                    "clone",
+                    "--mirror",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.progress = true),
+                vec![
+                    "clone",
+                    "--progress",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| o.recurse_submodules = true),
+                vec![
+                    "clone",
+                    "--recurse-submodules",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+            (
+                options(|o| {
+                    o.depth = Some(1);
+                    o.shallow_submodules = true;
+                }),
+                vec![
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--single-branch",
+                    "--recurse-submodules",
+                    "--shallow-submodules",
+                    "--",
+                    "git@github.com:team/project.git",
+                    "/src/project",
+                ],
+            ),
+        ];
+
+        for (options, expected) in cases {
+            let args = clone_args(&options, "git@github.com:team/project.git", "/src/project");
+            assert_eq!(args, expected);
+        }
+    }
+
+    #[test]
+    fn test_check_conflicts() {
+        let cases = vec![
+            (options(|_| {}), true),
+            (options(|o| o.bare = true), true),
+            (options(|o| o.unshallow = true), true),
+            (
+                options(|o| {
+                    o.unshallow = true;
+                    o.update = true;
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.unshallow = true;
+                    o.depth = Some(1);
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.depth = Some(1);
+                    o.shallow_since = Some("2024-01-31".to_string());
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.bare = true;
+                    o.tag = Some("v1.0.0".to_string());
+                }),
+                true,
+            ),
+            (
+                options(|o| {
+                    o.branch = Some("main".to_string());
+                    o.tag = Some("v1.0.0".to_string());
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.bare = true;
+                    o.branch = Some("main".to_string());
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.bare = true;
+                    o.commit = Some("abc123".to_string());
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.mirror = true;
+                    o.tag = Some("v1.0.0".to_string());
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.mirror = true;
+                    o.commit = Some("abc123".to_string());
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.worktree = Some("feature".to_string());
+                    o.commit = Some("abc123".to_string());
+                }),
+                true,
+            ),
+            (
+                options(|o| {
+                    o.worktree = Some("feature".to_string());
+                    o.depth = Some(1);
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.worktree = Some("feature".to_string());
+                    o.bare = true;
+                }),
+                false,
+            ),
+            (
+                options(|o| {
+                    o.skip_existing = true;
+                    o.force = true;
+                }),
+                true,
+            ),
+            (
+                options(|o| {
+                    o.skip_existing = true;
+                    o.update = true;
+                }),
+                false,
+            ),
+        ];
+
+        for (options, valid) in cases {
+            assert_eq!(check_conflicts(&options).is_ok(), valid, "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn test_fetch_branch_args() {
+        let cases = vec![
+            (options(|_| {}), vec!["fetch", "--", "origin", "main:main"]),
+            (
+                options(|o| o.depth = Some(1)),
+                vec!["fetch", "--depth", "1", "--", "origin", "main:main"],
+            ),
+            (
+                options(|o| {
+                    o.shallow_since = Some("2024-01-31".to_string());
+                    o.remote = Some("upstream".to_string());
+                }),
+                vec!["fetch", "--depth", "1", "--", "upstream", "main:main"],
+            ),
+        ];
+
+        for (options, expected) in cases {
+            assert_eq!(
+                fetch_branch_args(&options, "main"),
+                expected,
+                "{:?}",
+                options
+            );
+        }
+    }
+
+    #[test]
+    fn test_describe_destination() {
+        let cases = vec![
+            (false, None, false, "it's not a git repository"),
+            (
+                true,
+                Some("main"),
+                false,
+                "it's a git repository on branch main, with no uncommitted changes",
+            ),
+            (
+                true,
+                Some("feature/login"),
+                true,
+                "it's a git repository on branch feature/login, with uncommitted changes",
+            ),
+            (
+                true,
+                None,
+                false,
+                "it's a git repository with a detached HEAD, with no uncommitted changes",
+            ),
+        ];
+
+        for (is_repo, branch, dirty, expected) in cases {
+            assert_eq!(describe_destination(is_repo, branch, dirty), expected);
+        }
+    }
+
+    #[test]
+    fn test_backup_path() {
+        let cases = vec![
+            (
+                0,
+                "/src/team/project",
+                "/src/team/project.bak.19700101T000000",
+            ),
+            (
+                1_704_110_400,
+                "/src/team/project",
+                "/src/team/project.bak.20240101T120000",
+            ),
+            (
+                1_709_251_199,
+                "/src/team/project/",
+                "/src/team/project.bak.20240229T235959",
+            ),
+        ];
+
+        for (secs, project_path, expected) in cases {
+            let now = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(backup_path(project_path, now), expected);
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let cases = vec![
+            (Duration::from_millis(0), "0.0s"),
+            (Duration::from_millis(12_345), "12.3s"),
+            (Duration::from_millis(59_999), "59.9s"),
+            (Duration::from_secs(60), "1m 00s"),
+            (Duration::from_secs(185), "3m 05s"),
+        ];
+
+        for (duration, expected) in cases {
+            assert_eq!(format_duration(duration), expected);
+        }
+    }
+
+    #[test]
+    fn test_worktree_path() {
+        let cases = vec![
+            (
+                "/src/github.com/team/project",
+                "main",
+                "/src/github.com/team/project@main",
+            ),
+            (
+                "/src/github.com/team/project",
+                "feature/login",
+                "/src/github.com/team/project@feature-login",
+            ),
+            (
+                "/src/github.com/team/project",
+                "v1.0",
+                "/src/github.com/team/project@v1.0",
+            ),
+        ];
+
+        for (project_path, branch, expected) in cases {
+            assert_eq!(
+                worktree_path(project_path, branch),
+                expected,
+                "branch: {}",
+                branch
+            );
+        }
+    }
+
+    #[test]
+    fn test_same_repository() {
+        let cases = vec![
+            (
+                "git@github.com:team/project.git",
+                "git@github.com:team/project.git",
+                true,
+            ),
+            (
+                "https://github.com/team/project",
+                "git@github.com:team/project.git",
+                true,
+            ),
+            (
+                "https://github.com/Team/Project.git",
+                "git@github.com:team/project.git",
+                true,
+            ),
+            (
+                "git@github.com:team/other.git",
+                "git@github.com:team/project.git",
+                false,
+            ),
+            (
+                "git@gitlab.com:team/project.git",
+                "git@github.com:team/project.git",
+                false,
+            ),
+            (
+                "/srv/git/project.git",
+                "git@github.com:team/project.git",
+                false,
+            ),
+        ];
+
+        for (url, clone_url, expected) in cases {
+            assert_eq!(
+                same_repository(url, clone_url, DEFAULT_HOST),
+                expected,
+                "url: {:?}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_clone_dir() {
+        let cases = vec![
+            ("/src/github.com/team/project", "/src/github.com/team"),
+            ("/src/project.git", "/src"),
+            ("/project", "/"),
+            ("/", "/"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(clone_dir(input), Path::new(expected), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_clone_protocol() {
+        let cases = vec![
+            (
+                options(|_| {}),
+                "team/project",
+                "git@github.com:team/project.git",
+            ),
+            (
+                options(|_| {}),
+                "https://github.com/team/project",
+                "https://github.com/team/project.git",
+            ),
+            (
+                options(|o| o.protocol = Some(Protocol::Ssh)),
+                "https://github.com/team/project",
+                "git@github.com:team/project.git",
+            ),
+            (
+                options(|o| {
+                    o.protocol = Some(Protocol::Ssh);
+                    o.default_protocol = Protocol::Https;
+                }),
+                "team/project",
+                "git@github.com:team/project.git",
+            ),
+            (
+                options(|o| o.protocol = Some(Protocol::Https)),
+                "git@github.com:team/project.git",
+                "https://github.com/team/project.git",
+            ),
+            (
+                options(|o| o.default_protocol = Protocol::Https),
+                "git@github.com:team/project.git",
+                "git@github.com:team/project.git",
+            ),
+        ];
+
+        for (options, input, expected) in cases {
+            let repo = parser::repository(input.to_string()).unwrap();
+            let protocol = clone_protocol(&options, &repo);
+            assert_eq!(clone_url(&repo, protocol), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_clone_url() {
+        let repo = |port| parser::Repository {
+            host: "gitlab.com".to_string(),
+            port,
+            protocol: None,
+            team: "group/subgroup".to_string(),
+            project: "project".to_string(),
+        };
+        let gist = parser::Repository {
+            host: "gist.github.com".to_string(),
+            port: None,
+            protocol: None,
+            team: "user".to_string(),
+            project: "0123abcd".to_string(),
+        };
+        let https_repo = |port| parser::Repository {
+            host: "gitlab.com".to_string(),
+            port,
+            protocol: Some(Protocol::Https),
+            team: "group/subgroup".to_string(),
+            project: "project".to_string(),
+        };
+        let git_repo = |port| parser::Repository {
+            host: "gitlab.com".to_string(),
+            port,
+            protocol: Some(Protocol::Git),
+            team: "group/subgroup".to_string(),
+            project: "project".to_string(),
+        };
+        let sourcehut = parser::Repository {
+            host: "git.sr.ht".to_string(),
+            port: None,
+            protocol: None,
+            team: "~user".to_string(),
+            project: "project".to_string(),
+        };
+
+        let cases = vec![
+            (
+                repo(None),
+                Protocol::Ssh,
+                "git@gitlab.com:group/subgroup/project.git",
+            ),
+            (
+                repo(Some(2222)),
+                Protocol::Ssh,
+                "ssh://git@gitlab.com:2222/group/subgroup/project.git",
+            ),
+            (
+                repo(None),
+                Protocol::Https,
+                "https://gitlab.com/group/subgroup/project.git",
+            ),
+            (
+                repo(Some(2222)),
+                Protocol::Https,
+                "https://gitlab.com/group/subgroup/project.git",
+            ),
+            (
+                https_repo(Some(8443)),
+                Protocol::Https,
+                "https://gitlab.com:8443/group/subgroup/project.git",
+            ),
+            (
+                https_repo(Some(8443)),
+                Protocol::Ssh,
+                "git@gitlab.com:group/subgroup/project.git",
+            ),
+            (
+                git_repo(None),
+                Protocol::Git,
+                "git://gitlab.com/group/subgroup/project.git",
+            ),
+            (
+                git_repo(Some(9418)),
+                Protocol::Git,
+                "git://gitlab.com:9418/group/subgroup/project.git",
+            ),
+            (
+                git_repo(Some(9418)),
+                Protocol::Ssh,
+                "git@gitlab.com:group/subgroup/project.git",
+            ),
+            (
+                repo(None),
+                Protocol::Git,
+                "git://gitlab.com/group/subgroup/project.git",
+            ),
+            (
+                gist.clone(),
+                Protocol::Ssh,
+                "git@gist.github.com:0123abcd.git",
+            ),
+            (
+                gist,
+                Protocol::Https,
+                "https://gist.github.com/0123abcd.git",
+            ),
+            (
+                sourcehut.clone(),
+                Protocol::Ssh,
+                "git@git.sr.ht:~user/project",
+            ),
+            (
+                sourcehut,
+                Protocol::Https,
+                "https://git.sr.ht/~user/project",
+            ),
+        ];
+
+        for (repo, protocol, expected) in cases {
+            assert_eq!(clone_url(&repo, protocol), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_protocol() {
+        let cases = vec![
+            ("ssh", Some(Protocol::Ssh)),
+            ("https", Some(Protocol::Https)),
+            ("HTTPS", Some(Protocol::Https)),
+            ("http", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_protocol(input).ok(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_depth() {
+        let cases = vec![
+            ("1", Some(1)),
+            ("50", Some(50)),
+            (" 10 ", Some(10)),
+            ("0", None),
+            ("-1", None),
+            ("abc", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_depth(input).ok(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_branch() {
+        let cases = vec![
+            ("main", true),
+            ("feature/login", true),
+            ("release-1.0", true),
+            ("fix_#123", true),
+            ("", false),
+            ("-b", false),
+            ("--upload-pack=touch", false),
+            ("my branch", false),
+            ("tab\there", false),
+            ("new\nline", false),
+            ("head~1", false),
+            ("a:b", false),
+            ("what?", false),
+            ("a..b", false),
+            ("a@{1}", false),
+            ("feature/", false),
+            ("/feature", false),
+            ("main.", false),
+        ];
+
+        for (input, valid) in cases {
+            assert_eq!(parse_branch(input).is_ok(), valid, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let cases = vec![
+            ("work", Some("work")),
+            ("work/backend", Some("work/backend")),
+            ("/work//backend/", Some("work/backend")),
+            ("./work", Some("work")),
+            ("work\\backend", Some("work/backend")),
+            ("", None),
+            ("/", None),
+            ("..", None),
+            ("work/../..", None),
+            ("..\\work", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_prefix(input).ok().as_deref(),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_name() {
+        let cases = vec![
+            ("project-2", true),
+            ("project.old", true),
+            ("", false),
+            (".", false),
+            ("..", false),
+            ("team/project", false),
+            ("..\\project", false),
+        ];
+
+        for (input, valid) in cases {
+            assert_eq!(parse_name(input).is_ok(), valid, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_resolve_base_dir() {
+        let base_dir = env::temp_dir().join(format!("gc-rust-test-base-{}", std::process::id()));
+        fs::create_dir_all(&base_dir).unwrap();
+        let file = base_dir.join("file");
+        fs::write(&file, "").unwrap();
+
+        let cases = vec![
+            (base_dir.display().to_string(), Some(base_dir.clone())),
+            (base_dir.join("missing").display().to_string(), None),
+            (file.display().to_string(), None),
+        ];
+
+        for (configured, expected) in cases {
+            let resolved = resolve_base_dir(Some(&configured), DEFAULT_BASE_SUBDIR, "git");
+            assert_eq!(resolved.ok(), expected, "configured: {}", configured);
+        }
+
+        fs::remove_dir_all(&base_dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_git() {
+        let cases = vec![
+            ("git", true),
+            ("gc-rust-missing-git", false),
+            ("/nonexistent/git", false),
+        ];
+
+        for (binary, expected) in cases {
+            assert_eq!(check_git(binary).is_ok(), expected, "binary: {}", binary);
+        }
+    }
+
+    #[test]
+    fn test_destination() {
+        let repo = repository("github.com/team/project".to_string()).unwrap();
+        let cases = vec![
+            (options(|_| {}), "/src/github.com/team/project"),
+            (
+                options(|o| o.prefix = Some("work/backend".to_string())),
+                "/src/work/backend/github.com/team/project",
+            ),
+            (
+                options(|o| o.name = Some("project-2".to_string())),
+                "/src/github.com/team/project-2",
+            ),
+            (
+                options(|o| {
+                    o.name = Some("project-2".to_string());
+                    o.bare = true;
+                }),
+                "/src/github.com/team/project-2.git",
+            ),
+        ];
+
+        for (options, expected) in cases {
+            assert_eq!(destination(&options, &repo).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let cases = vec![
+            ("2024-01-31", Some("2024-01-31")),
+            (" 2 weeks ago ", Some("2 weeks ago")),
+            ("", None),
+            ("   ", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_since(input).ok().as_deref(), expected);
+        }
+    }
+
+    #[test]
+    fn test_level() {
+        let cases = vec![
+            (options(|_| {}), LogLevel::Info),
+            (options(|o| o.quiet = true), LogLevel::Error),
+            (options(|o| o.verbose = true), LogLevel::Debug),
+            (
+                options(|o| {
+                    o.quiet = true;
+                    o.log_level = Some(LogLevel::Warn);
+                }),
+                LogLevel::Warn,
+            ),
+            (
+                options(|o| o.log_level = Some(LogLevel::Debug)),
+                LogLevel::Debug,
+            ),
+        ];
+
+        for (options, expected) in cases {
+            assert_eq!(options.level(), expected, "{:?}", options);
+        }
+
+        let cases = vec![
+            ("error", Some(LogLevel::Error)),
+            (" Warn ", Some(LogLevel::Warn)),
+            ("info", Some(LogLevel::Info)),
+            ("DEBUG", Some(LogLevel::Debug)),
+            ("trace", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_log_level(input).ok(), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let cases = vec![
+            ("blob:none", Some("blob:none")),
+            (" tree:0 ", Some("tree:0")),
+            ("blob:limit=1024", Some("blob:limit=1024")),
+            ("blob:limit=1m", Some("blob:limit=1m")),
+            ("object:type=commit", Some("object:type=commit")),
+            ("sparse:oid=main:.sparse", Some("sparse:oid=main:.sparse")),
+            ("combine:blob:none+tree:1", Some("combine:blob:none+tree:1")),
+            ("blob:nnone", None),
+            ("blob:limit=", None),
+            ("blob:limit=1x", None),
+            ("tree:", None),
+            ("tree:-1", None),
+            ("object:type=file", None),
+            ("sparse:oid=", None),
+            ("combine:blob:none+", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_filter(input).ok().as_deref(),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_retries() {
+        let cases = vec![
+            ("0", Some(0)),
+            ("3", Some(3)),
+            (" 5 ", Some(5)),
+            ("-1", None),
+            ("many", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_retries(input).ok(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_jobs() {
+        let cases = vec![
+            ("1", Some(1)),
+            (" 8 ", Some(8)),
+            ("0", None),
+            ("-2", None),
+            ("all", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_jobs(input).ok(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let cases = vec![
+            ("1", Some(Duration::from_secs(1))),
+            (" 300 ", Some(Duration::from_secs(300))),
+            ("0", None),
+            ("-5", None),
+            ("1.5", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_timeout(input).ok(), expected);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::ApplicationError;
+use crate::git::{is_bare_repository, is_git_repository};
+
+// How many directories deep repositories are looked for. The default layout
+// puts them three levels down, and GitLab subgroups add a few more.
+const MAX_DEPTH: usize = 6;
+
+/// Lists the repositories cloned inside the base directory, as paths relative
+/// to it like `github.com/team/project`, sorted alphabetically.
+pub fn cloned_repos(base_dir: &str) -> Result<Vec<String>, ApplicationError> {
+    let base_dir = Path::new(base_dir);
+    // Only an unreadable base directory is an error, anything below it that
+    // can't be read is skipped
+    fs::read_dir(base_dir).map_err(ApplicationError::BaseDirCannotBeOpened)?;
+
+    let mut repos = Vec::new();
+    walk(base_dir, base_dir, 0, &mut repos);
+    repos.sort();
+    Ok(repos)
+}
+
+fn walk(base_dir: &Path, dir: &Path, depth: usize, repos: &mut Vec<String>) {
+    if depth > 0 && (is_git_repository(dir) || is_bare_repository(dir)) {
+        if let Ok(relative) = dir.strip_prefix(base_dir) {
+            repos.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+        return;
+    }
+
+    if depth == MAX_DEPTH {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        // Symlinks aren't followed, so a link back up the tree can't loop
+        if !hidden && entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            walk(base_dir, &entry.path(), depth + 1, repos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_cloned_repos() {
+        let base_dir = env::temp_dir().join(format!("gc-rust-test-list-{}", std::process::id()));
+        let dirs = vec![
+            "github.com/team/project/.git",
+            "github.com/team/project/vendor/nested/.git",
+            "github.com/team/other/.git",
+            "github.com/team/bare.git/objects",
+            "github.com/team/bare.git/refs",
+            "github.com/team/not-bare.git/objects",
+            "gitlab.com/group/subgroup/project/.git",
+            "github.com/team/not-a-repo/src",
+            ".cache/hidden/.git",
+        ];
+        for dir in &dirs {
+            fs::create_dir_all(base_dir.join(dir)).unwrap();
+        }
+        fs::write(
+            base_dir.join("github.com/team/bare.git/HEAD"),
+            "ref: refs/heads/main\n",
+        )
+        .unwrap();
+
+        let repos = cloned_repos(&base_dir.to_string_lossy());
+        fs::remove_dir_all(&base_dir).unwrap();
+
+        assert_eq!(
+            repos.unwrap(),
+            vec![
+                "github.com/team/bare.git",
+                "github.com/team/other",
+                "github.com/team/project",
+                "gitlab.com/group/subgroup/project",
+            ]
+        );
+    }
+}
@@ -1,176 +1,932 @@
-use getopts::Options;
-use std::fmt::{Display, Formatter};
-use std::path::Path;
-use std::{env, fmt};
-use std::{fs, io};
-use subprocess::{Exec, Redirection};
-
-mod parser;
-
-enum ApplicationError {
-    BaseDirNotFound,
-    BaseDirCannotBeOpened(std::io::Error),
-    CantCreateTargetDir(std::io::Error),
-    CantDeleteTargetDir(std::io::Error),
-    FailedCloneCommand(subprocess::PopenError),
-    FailedCheckoutCommand(subprocess::PopenError),
-    FailedGitOperation(),
-    FailedParsingRepo(parser::ParseRepoError),
-    FailedCaptureInput(std::io::Error),
-    ArgumentParsingError(getopts::Fail),
-}
-
-impl Display for ApplicationError {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match self {
-            ApplicationError::BaseDirNotFound => {
-                write!(f, "The base directory on which to download the repositories was not found. Ensure you have set the $GC_DOWNLOAD_PATH or $GOPATH environment variable.")
-            }
-            ApplicationError::BaseDirCannotBeOpened(err) => {
-                write!(f, "Base directory cannot be opened: {}", err)
-            }
-            ApplicationError::CantCreateTargetDir(err) => {
-                write!(f, "Cannot create target directory: {}", err)
-            }
-            ApplicationError::CantDeleteTargetDir(err) => {
-                write!(f, "Cannot delete target directory: {}", err)
-            }
-            ApplicationError::FailedCloneCommand(err) => {
-                write!(f, "Failed to run the git clone command: {}", err)
-            }
-            ApplicationError::FailedCheckoutCommand(err) => {
-                write!(f, "Failed to run the git checkout command: {}", err)
-            }
-            ApplicationError::FailedGitOperation() => {
-                write!(f, "Failed to clone the repo.")
-            }
-            ApplicationError::FailedCaptureInput(err) => {
-                write!(f, "Failed to capture prompt: {}", err)
-            }
-            ApplicationError::FailedParsingRepo(err) => {
-                write!(f, "Failed to parse the repository URL: {}", err)
+use std::env;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use gc_rust::{
+    check_git, check_writable, clone_repo, cloned_repos, expand_alias, paint, parse_branch,
+    parse_depth, parse_filter, parse_jobs, parse_log_level, parse_name, parse_prefix,
+    parse_protocol, parse_retries, parse_since, parse_timeout, repo_path, repository_with_host,
+    resolve_base_dir, strip_credentials, Action, ApplicationError, CloneOptions, CloneOutcome,
+    Color, Icon, LogLevel, Protocol, Repository, DEFAULT_BASE_SUBDIR,
+};
+
+use config::Config;
+
+mod config;
+mod editor;
+mod flags;
+mod json;
+mod pick;
+mod shell;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (args, git_args) = split_git_args(args);
+    let (json, plain, result) = match flags::options().parse(&args) {
+        Ok(matches) => (
+            matches.opt_present("json"),
+            is_plain(Some(&matches)),
+            run(&matches, git_args),
+        ),
+        Err(err) => (
+            false,
+            is_plain(None),
+            Err(ApplicationError::ArgumentParsingError(err)),
+        ),
+    };
+
+    if let Err(err) = result {
+        // What was left untouched is still worth going to, so its path is
+        // printed like a clone's would be, but with the exit code of a cancel
+        let path = match &err {
+            ApplicationError::Cancelled(path) => Some(path),
+            _ => None,
+        };
+        if json {
+            let result = match path {
+                Some(path) => json::Object::new().field("path", path),
+                None => json::Object::new(),
+            };
+            println!("{}", result.field("error", err.to_string()));
+        } else {
+            if let Some(path) = path {
+                println!("{}", path);
             }
-            ApplicationError::ArgumentParsingError(err) => {
-                write!(f, "Failed to parse arguments: {}", err)
+
+            if matches!(
+                err,
+                ApplicationError::Cancelled(_) | ApplicationError::NothingPicked
+            ) {
+                eprintln!(
+                    "{}",
+                    paint(
+                        Color::Yellow,
+                        format_args!("{} {}", Icon::Warning.text(plain), err)
+                    )
+                );
+            } else {
+                eprintln!(
+                    "{}",
+                    paint(
+                        Color::Red,
+                        format_args!("{} Error: {}", Icon::Error.text(plain), err)
+                    )
+                );
             }
         }
+        std::process::exit(err.exit_code());
     }
 }
 
-impl From<parser::ParseRepoError> for ApplicationError {
-    fn from(err: parser::ParseRepoError) -> Self {
-        ApplicationError::FailedParsingRepo(err)
-    }
+// Plain output is asked for with --plain or $GC_PLAIN, which is all there is
+// to go on when the flags themselves can't be parsed.
+fn is_plain(matches: Option<&getopts::Matches>) -> bool {
+    matches.is_some_and(|matches| matches.opt_present("plain")) || env_flag("GC_PLAIN")
 }
 
-fn main() {
-    match run() {
-        Ok(_) => {}
-        Err(err) => {
-            eprintln!("\u{f071} Error: {}", err);
-            std::process::exit(1);
+// Pairs of flags that can't be used together, whatever else is asked for.
+const CONFLICTS: &[(&str, &str)] = &[
+    ("json", "print-path-only"),
+    // These print something other than the destination of a clone
+    ("print-path-only", "init"),
+    ("print-path-only", "completions"),
+    ("print-path-only", "complete-repos"),
+    ("print-path-only", "parse"),
+    ("print-path-only", "list"),
+    ("print-path-only", "where"),
+    ("log-level", "quiet"),
+    ("log-level", "verbose"),
+];
+
+fn check_conflicts(matches: &getopts::Matches) -> Result<(), ApplicationError> {
+    for (first, second) in CONFLICTS {
+        if matches.opt_present(first) && matches.opt_present(second) {
+            return Err(ApplicationError::ConflictingOptions(
+                format!("--{}", first),
+                format!("--{}", second),
+            ));
         }
     }
+
+    Ok(())
 }
 
-fn run() -> Result<(), ApplicationError> {
+fn run(matches: &getopts::Matches, git_args: Vec<String>) -> Result<(), ApplicationError> {
+    check_conflicts(matches)?;
+
+    if let Some(shell) = matches.opt_str("init") {
+        let script = shell::init_script(&shell, flags::FLAGS)
+            .ok_or(ApplicationError::UnsupportedShell(shell))?;
+        print!("{}", script);
+        return Ok(());
+    }
+
+    if let Some(shell) = matches.opt_str("completions") {
+        let script = shell::completion_script(&shell, flags::FLAGS)
+            .ok_or(ApplicationError::UnsupportedShell(shell))?;
+        print!("{}", script);
+        return Ok(());
+    }
+
+    let config = Config::load(matches.opt_str("config-path").as_deref())?;
+    let profile = matches
+        .opt_str("profile")
+        .or_else(|| env::var("GC_PROFILE").ok())
+        .filter(|profile| !profile.is_empty());
+    let config = match profile {
+        Some(profile) => config.with_profile(&profile)?,
+        None => config,
+    };
+
+    // Only the parsing rules are needed, nothing is looked up or created
+    if matches.opt_present("parse") {
+        if matches.free.is_empty() {
+            eprintln!("Usage: gc --parse <repository-url>... [--json]");
+            return Ok(());
+        }
+
+        let options = clone_options(matches, &config, "")?;
+        for repo_url in &matches.free {
+            let repo_url = expand_alias(repo_url, &options.host_aliases);
+            let repo = repository_with_host(repo_url, &options.default_host)?;
+            print_parsed(matches, &repo);
+        }
+        return Ok(());
+    }
+
     // Get the base directory
-    let base_dir = env::var("GC_DOWNLOAD_PATH")
-        .or_else(|_| env::var("GOPATH"))
-        .map_err(|_| ApplicationError::BaseDirNotFound)?;
-    let base_dir = format!("{}/src", base_dir);
-
-    // Try opening the base directory
-    fs::read_dir(&base_dir).map_err(ApplicationError::BaseDirCannotBeOpened)?;
-
-    // Get the repository URL from the command line arguments
-    let args: Vec<String> = env::args().collect();
-    let mut opts = Options::new();
-    opts.optopt(
-        "b",
-        "branch",
-        "set the branch to checkout after cloning",
-        "BRANCH",
+    let base_subdir = match matches.opt_str("base-subdir") {
+        Some(subdir) => subdir,
+        None if env_flag("GC_NO_SRC_SUBDIR") => String::new(),
+        None => DEFAULT_BASE_SUBDIR.to_string(),
+    };
+    let base_dir = resolve_base_dir(
+        config.base_dir.as_deref(),
+        &base_subdir,
+        &git_binary(matches).unwrap_or_else(|| "git".to_string()),
     );
 
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(f) => return Err(ApplicationError::ArgumentParsingError(f)),
+    // Completion scripts run this on every tab, where an error message would
+    // only get in the way
+    if matches.opt_present("complete-repos") {
+        let repos = match &base_dir {
+            Ok(base_dir) => cloned_repos(&base_dir.display().to_string()).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        for repo in repos {
+            println!("{}", repo);
+        }
+        return Ok(());
+    }
+
+    // Resolved and checked once, for every repository of the run
+    let base_dir = base_dir?.display().to_string();
+
+    if matches.opt_present("pick") {
+        let repos = cloned_repos(&base_dir)?;
+        if repos.is_empty() {
+            eprintln!("No repositories have been cloned into {} yet.", base_dir);
+            return Ok(());
+        }
+
+        let repo = pick::pick(&repos, io::stdin().lock())?;
+        println!("{}", repo_dir(&base_dir, &repo));
+        return Ok(());
+    }
+
+    if matches.opt_present("list") {
+        for repo in cloned_repos(&base_dir)? {
+            if matches.opt_present("json") {
+                let result = json::Object::new()
+                    .field("repository", &repo)
+                    .field("path", repo_dir(&base_dir, &repo));
+                println!("{}", result);
+            } else {
+                println!("{}", repo);
+            }
+        }
+        return Ok(());
+    }
+
+    // The ones after "--" go last, so they win over the persistent ones
+    let mut clone_args = match env::var("GC_CLONE_ARGS") {
+        Ok(args) => split_args(&args).ok_or(ApplicationError::InvalidCloneArgs(args))?,
+        Err(_) => Vec::new(),
+    };
+    clone_args.extend(git_args);
+    let options = CloneOptions {
+        git_args: clone_args,
+        ..clone_options(matches, &config, &base_dir)?
     };
 
-    let repo_url = if !matches.free.is_empty() {
-        matches.free[0].clone()
-    } else {
-        eprintln!("Usage: gc <repository-url> [-b <branch>]");
+    let jobs = match matches.opt_str("jobs") {
+        Some(jobs) => parse_jobs(&jobs)?,
+        None => 1,
+    };
+
+    // Finding the path doesn't run git, everything else does
+    if !matches.opt_present("where") {
+        check_git(&options.git_binary)?;
+    }
+
+    // Checked once for the whole run, since every clone shares the base
+    // directory. Nothing is created in it when only updating or previewing.
+    if !matches.opt_present("where")
+        && options.output_dir.is_none()
+        && !options.dry_run
+        && !options.update
+        && !options.unshallow
+    {
+        check_writable(&base_dir)?;
+    }
+
+    if matches.opt_present("stdin") {
+        if !matches.free.is_empty() {
+            return Err(ApplicationError::StdinWithArguments);
+        }
+
+        let repo_urls = read_repo_urls(io::stdin())?;
+        return clone_batch(matches, &options, &repo_urls, jobs);
+    }
+
+    // Get the repository URLs from the command line arguments
+    let repo_urls = &matches.free;
+    if repo_urls.is_empty() {
+        eprintln!("Usage: gc <repository-url>... [-b <branch>] [-n] [-f] [-u] [-q] [-v] [--json]");
         return Ok(());
+    }
+
+    if matches.opt_present("where") {
+        for repo_url in repo_urls {
+            let path = repo_path(&CloneOptions {
+                repo_url: repo_url.clone(),
+                ..options.clone()
+            })?;
+            println!("{}", path);
+        }
+        return Ok(());
+    }
+
+    if repo_urls.len() == 1 {
+        let open = matches.opt_present("open") && !options.dry_run;
+        let (plain, level) = (options.plain, options.level());
+        let outcome = clone_repo(CloneOptions {
+            repo_url: repo_urls[0].clone(),
+            ..options
+        })?;
+        print_result(matches, &outcome);
+        if open {
+            open_editor(&outcome.path, plain, level)?;
+        }
+        return Ok(());
+    }
+
+    clone_batch(matches, &options, repo_urls, jobs)
+}
+
+// Clones each repository with the same options, up to `jobs` of them at once.
+fn clone_batch(
+    matches: &getopts::Matches,
+    options: &CloneOptions,
+    repo_urls: &[String],
+    jobs: usize,
+) -> Result<(), ApplicationError> {
+    // Every repository would end up in the same directory
+    if options.output_dir.is_some() {
+        return Err(ApplicationError::OutputWithMultipleRepos);
+    }
+
+    // One editor per repository would be more noise than help
+    if matches.opt_present("open") {
+        return Err(ApplicationError::OpenWithMultipleRepos);
+    }
+
+    // Clones running at the same time can't share the terminal: the messages
+    // of each one, the output of git included, are printed together once
+    // it's done, and nothing is asked
+    let options = if jobs > 1 {
+        CloneOptions {
+            buffer_output: true,
+            no_prompt: true,
+            ..options.clone()
+        }
+    } else {
+        options.clone()
     };
 
-    let branch = matches.opt_str("b");
+    // Every worker takes the next repository until there are none left, and
+    // sends back its result to be printed here, one at a time. Ctrl+C reaches
+    // the git processes too, since they're in the same process group, so
+    // they stop along with gc-rust.
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut failed = 0;
+    let mut actions = Vec::new();
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(repo_urls.len()) {
+            let (next, options, sender) = (&next, &options, sender.clone());
+            scope.spawn(move || {
+                while let Some(repo_url) = repo_urls.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = clone_repo(CloneOptions {
+                        repo_url: repo_url.clone(),
+                        ..options.clone()
+                    });
+                    if sender.send((repo_url, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Keep going when a repository fails to clone, so one bad URL doesn't
+        // stop the rest of the batch
+        for (repo_url, result) in receiver {
+            match result {
+                Ok(outcome) => {
+                    actions.push(outcome.action);
+                    print_result(matches, &outcome);
+                }
+                Err(err) => {
+                    failed += 1;
+                    print_batch_error(matches, repo_url, &err);
+                }
+            }
+        }
+    });
+
+    if failed > 0 {
+        return Err(ApplicationError::FailedRepositories(
+            failed,
+            repo_urls.len(),
+        ));
+    }
+
+    if options.level() >= LogLevel::Info && !options.dry_run {
+        eprintln!(
+            "{}",
+            paint(
+                Color::Green,
+                format_args!(
+                    "{} {}",
+                    Icon::Success.text(options.plain),
+                    describe_batch(&actions)
+                )
+            )
+        );
+    }
+    Ok(())
+}
+
+// Sums up a batch where every repository went fine, saying how many were
+// updated or skipped instead of cloned, if any.
+fn describe_batch(actions: &[Action]) -> String {
+    let count = |action| actions.iter().filter(|&&a| a == action).count();
+    if count(Action::Cloned) == actions.len() {
+        return format!("Successfully cloned all {} repositories.", actions.len());
+    }
 
-    // Parse the repository URL
-    let (host, team, project) = parser::repository(repo_url.to_string())?;
-    let project_path = format!("{}/{}/{}/{}", base_dir, host, team, project);
-    let clone_url = format!("git@{}:{}/{}.git", host, team, project);
+    let parts: Vec<String> = [
+        (Action::Cloned, "cloned"),
+        (Action::Updated, "updated"),
+        (Action::Skipped, "skipped"),
+    ]
+    .into_iter()
+    .filter(|&(action, _)| count(action) > 0)
+    .map(|(action, name)| format!("{} {}", count(action), name))
+    .collect();
+    format!(
+        "Done with all {} repositories: {}.",
+        actions.len(),
+        parts.join(", ")
+    )
+}
 
-    // Create the directory if it does not exist
-    if !Path::new(&project_path).exists() {
+// Opens the repository in the editor, which isn't worth failing over when
+// there's none set up.
+fn open_editor(path: &str, plain: bool, level: LogLevel) -> Result<(), ApplicationError> {
+    if !editor::open(path)? && level >= LogLevel::Warn {
         eprintln!(
-            "\u{ea83} Destination directory for {}/{} does not exist. Creating...",
-            team, project
+            "{}",
+            paint(
+                Color::Yellow,
+                format_args!(
+                    "{} Warning: set $VISUAL or $EDITOR to open {} in an editor.",
+                    Icon::Warning.text(plain),
+                    path
+                )
+            )
         );
-        fs::create_dir_all(&project_path).map_err(ApplicationError::CantCreateTargetDir)?;
+    }
+
+    Ok(())
+}
+
+// Joins a repository from cloned_repos, which always uses "/", to the base
+// directory with the separators of the platform.
+fn repo_dir(base_dir: &str, repo: &str) -> String {
+    let mut path = PathBuf::from(base_dir);
+    path.extend(repo.split('/'));
+    path.display().to_string()
+}
+
+// Splits off everything after the first "--", which is passed to git clone
+// as is instead of being parsed as gc's own flags.
+fn split_git_args(mut args: Vec<String>) -> (Vec<String>, Vec<String>) {
+    match args.iter().position(|arg| arg == "--") {
+        Some(index) => {
+            let git_args = args.split_off(index + 1);
+            args.pop();
+            (args, git_args)
+        }
+        None => (args, Vec::new()),
+    }
+}
+
+// Splits a string into arguments like a shell would, with single quotes taking
+// everything literally and double quotes and backslashes escaping spaces. An
+// unclosed quote or a trailing backslash can't be split.
+fn split_args(input: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => arg.push(chars.next()?),
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).push(chars.next()?),
+            c if c.is_whitespace() => args.extend(current.take()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    args.extend(current);
+    Some(args)
+}
+
+// Reads the repositories to clone, one per line, skipping blank lines and
+// comments. The whole input is read upfront, so nothing is left for the
+// confirmation prompt: pass --force or --update when destinations may exist.
+fn read_repo_urls(mut input: impl Read) -> Result<Vec<String>, ApplicationError> {
+    let mut contents = String::new();
+    input
+        .read_to_string(&mut contents)
+        .map_err(ApplicationError::FailedReadingStdin)?;
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// Builds the options shared by every repository being cloned. Command line
+// flags win over the config file, which wins over the environment.
+fn clone_options(
+    matches: &getopts::Matches,
+    config: &Config,
+    base_dir: &str,
+) -> Result<CloneOptions, ApplicationError> {
+    let mut options = CloneOptions::new("", base_dir);
+    options.branch = match matches.opt_str("b") {
+        Some(branch) => Some(parse_branch(&branch)?),
+        None => None,
+    };
+    options.tag = matches.opt_str("tag");
+    options.also_fetch_default_branch = matches.opt_present("also-fetch-default-branch");
+    options.worktree = match matches.opt_str("worktree") {
+        Some(branch) => Some(parse_branch(&branch)?),
+        None => None,
+    };
+    options.commit = matches.opt_str("commit");
+    options.output_dir = matches.opt_str("o");
+    options.name = match matches.opt_str("name") {
+        Some(name) => Some(parse_name(&name)?),
+        None => None,
+    };
+    options.remote = matches.opt_str("remote");
+    options.host_aliases = config.aliases.clone();
+    options.post_clone = matches
+        .opt_str("post-clone")
+        .or_else(|| config.post_clone.clone());
+    if let Some(git_binary) = git_binary(matches) {
+        options.git_binary = git_binary;
+    }
+    if let Some(host) = matches
+        .opt_str("host")
+        .or_else(|| config.host.clone())
+        .or_else(|| env::var("GC_DEFAULT_HOST").ok())
+        .filter(|host| !host.is_empty())
+    {
+        options.default_host = host;
+    }
+    if let Some(layout) = matches
+        .opt_str("layout")
+        .or_else(|| config.layout.clone())
+        .or_else(|| env::var("GC_LAYOUT").ok())
+    {
+        options.layout = layout;
+    }
+    options.prefix = match matches
+        .opt_str("prefix")
+        .or_else(|| env::var("GC_PATH_PREFIX").ok())
+        .filter(|prefix| !prefix.is_empty())
+    {
+        Some(prefix) => Some(parse_prefix(&prefix)?),
+        None => None,
+    };
+    options.dry_run = matches.opt_present("n");
+    options.force = matches.opt_present("f");
+    options.rename_existing = matches.opt_present("rename-existing");
+    options.skip_existing = matches.opt_present("skip-existing");
+    options.update = matches.opt_present("u");
+    options.plain = is_plain(Some(matches));
+    options.unshallow = matches.opt_present("unshallow");
+    options.quiet = matches.opt_present("q") || env_flag("GC_QUIET");
+    options.verbose = matches.opt_present("v");
+    options.quiet_git = matches.opt_present("quiet-git") || env_flag("GC_QUIET_GIT");
+    options.log_level = match matches.opt_str("log-level") {
+        Some(level) => Some(parse_log_level(&level)?),
+        None => None,
+    };
+    options.progress = matches.opt_present("progress");
+    options.no_prompt = matches.opt_present("no-prompt") || env_flag("GC_NO_PROMPT");
+    options.bare = matches.opt_present("bare");
+    options.mirror = matches.opt_present("mirror");
+    options.recurse_submodules =
+        matches.opt_present("recurse-submodules") || env_flag("GC_RECURSE_SUBMODULES");
+    options.shallow_submodules = matches.opt_present("shallow-submodules");
+    options.single_branch = matches.opt_present("single-branch");
+    options.no_checkout = matches.opt_present("no-checkout");
+    options.sparse = matches.opt_strs("sparse");
+    options.timeout = match matches.opt_str("timeout") {
+        Some(timeout) => Some(parse_timeout(&timeout)?),
+        None => None,
+    };
+    if let Some(retries) = matches.opt_str("retries") {
+        options.retries = parse_retries(&retries)?;
+    }
+    options.protocol = match matches.opt_str("protocol") {
+        Some(protocol) => Some(parse_protocol(&protocol)?),
+        None => None,
+    };
+    let mut protocol_flag = options.protocol.map(|_| "--protocol");
+    for (flag, protocol) in [
+        ("--force-ssh", Protocol::Ssh),
+        ("--force-https", Protocol::Https),
+    ] {
+        if !matches.opt_present(&flag[2..]) {
+            continue;
+        }
+        if let Some(other) = protocol_flag {
+            return Err(ApplicationError::ConflictingOptions(
+                other.to_string(),
+                flag.to_string(),
+            ));
+        }
+        protocol_flag = Some(flag);
+        options.protocol = Some(protocol);
+    }
+    if let Some(protocol) = config
+        .protocol
+        .clone()
+        .or_else(|| env::var("GC_PROTOCOL").ok())
+    {
+        options.default_protocol = parse_protocol(&protocol)?;
+    }
+    // A shallow clone by default is a depth of 1, for the branch being cloned
+    let shallow = env_flag("GC_CLONE_SHALLOW").then(|| "1".to_string());
+    options.depth = resolve_depth(
+        matches.opt_str("depth"),
+        matches.opt_present("full"),
+        config.depth.clone(),
+        env::var("GC_CLONE_DEPTH").ok().or(shallow),
+    )?;
+    options.shallow_since = match matches.opt_str("since") {
+        Some(since) => Some(parse_since(&since)?),
+        None => None,
+    };
+    options.filter = match matches.opt_str("filter") {
+        Some(filter) => Some(parse_filter(&filter)?),
+        None => None,
+    };
+
+    Ok(options)
+}
+
+// Picks the clone depth from the flags, then the config file, then the
+// environment. A depth of 0, or --full, asks for the full history and stops
+// the lookup there, so a configured shallow default can be overridden.
+fn resolve_depth(
+    flag: Option<String>,
+    full: bool,
+    config: Option<String>,
+    env: Option<String>,
+) -> Result<Option<u32>, ApplicationError> {
+    if full {
+        if flag.as_deref().is_some_and(|depth| depth.trim() != "0") {
+            return Err(ApplicationError::ConflictingOptions(
+                "--full".to_string(),
+                "--depth".to_string(),
+            ));
+        }
+        return Ok(None);
+    }
+
+    match flag.or(config).or(env) {
+        Some(depth) if depth.trim() == "0" => Ok(None),
+        Some(depth) => Ok(Some(parse_depth(&depth)?)),
+        None => Ok(None),
+    }
+}
+
+// The git binary asked for with --git-binary or $GC_GIT_BINARY, if any.
+fn git_binary(matches: &getopts::Matches) -> Option<String> {
+    matches
+        .opt_str("git-binary")
+        .or_else(|| env::var("GC_GIT_BINARY").ok())
+        .filter(|git_binary| !git_binary.is_empty())
+}
+
+// Reads a yes or no setting from an environment variable, where anything but
+// the usual ways of saying yes is a no, like an unset one.
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| is_truthy(&value))
+}
+
+// The usual ways of saying yes, in any case.
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes"
+    )
+}
+
+// Prints the destination directory to stdout, which is what shell functions
+// use to cd into the repository, or the whole result as JSON when requested.
+// Nothing else is ever printed to stdout.
+fn print_result(matches: &getopts::Matches, outcome: &CloneOutcome) {
+    if !matches.opt_present("json") {
+        println!("{}", outcome.path);
+        return;
+    }
+
+    let result = json::Object::new()
+        .field("host", &outcome.host)
+        .field("team", &outcome.team)
+        .field("project", &outcome.project)
+        .field("path", &outcome.path)
+        .field("clone_url", &outcome.clone_url)
+        .field("branch", outcome.branch.as_ref())
+        .field("tag", outcome.tag.as_ref())
+        .field("commit", outcome.commit.as_ref())
+        .field(
+            "duration_ms",
+            outcome.duration.map(|duration| duration.as_millis() as u64),
+        );
+    println!("{}", result);
+}
+
+// Prints the parts of a parsed repository, one per line so a shell can read
+// them into variables, or as JSON when requested.
+fn print_parsed(matches: &getopts::Matches, repo: &Repository) {
+    if matches.opt_present("json") {
+        let result = json::Object::new()
+            .field("host", &repo.host)
+            .field("team", &repo.team)
+            .field("project", &repo.project);
+        println!("{}", result);
+        return;
+    }
+
+    println!("{}\n{}\n{}", repo.host, repo.team, repo.project);
+}
+
+// Reports a repository that failed to clone as part of a batch, as its own
+// JSON object when requested so each line of stdout stays parseable. A
+// cancelled one still has its path printed, like a single repository.
+fn print_batch_error(matches: &getopts::Matches, repo_url: &str, err: &ApplicationError) {
+    let repo_url = strip_credentials(repo_url);
+    let path = match err {
+        ApplicationError::Cancelled(path) => Some(path),
+        _ => None,
+    };
+    if matches.opt_present("json") {
+        let result = json::Object::new().field("repository", &repo_url);
+        let result = match path {
+            Some(path) => result.field("path", path),
+            None => result,
+        };
+        println!("{}", result.field("error", err.to_string()));
     } else {
+        if let Some(path) = path {
+            println!("{}", path);
+        }
         eprintln!(
-            "\u{eb32} Destination directory for {}/{} already exists.",
-            team, project
+            "{}",
+            paint(
+                Color::Red,
+                format_args!(
+                    "{} Error cloning {}: {}",
+                    Icon::Error.text(is_plain(Some(matches))),
+                    repo_url,
+                    err
+                )
+            )
         );
-        eprintln!("Press <Enter> to confirm deletion or <Ctrl+C> to cancel...");
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .map_err(ApplicationError::FailedCaptureInput)?;
-        fs::remove_dir_all(&project_path).map_err(ApplicationError::CantDeleteTargetDir)?;
-        fs::create_dir_all(&project_path).map_err(ApplicationError::CantCreateTargetDir)?;
     }
+}
 
-    // Run the git clone command
-    eprintln!("\u{ebcc} Cloning {}/{}...", team, project);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let exec = Exec::cmd("git")
-        .args(&["clone", &clone_url, &project_path])
-        .cwd(env::temp_dir())
-        .stdout(Redirection::None)
-        .stderr(Redirection::None)
-        .capture()
-        .map_err(ApplicationError::FailedCloneCommand)?;
+    #[test]
+    fn test_read_repo_urls() {
+        let cases = vec![
+            ("", vec![]),
+            ("example/a\n", vec!["example/a"]),
+            (
+                "example/a\n\n# a comment\n  example/b  \r\n#example/c\n",
+                vec!["example/a", "example/b"],
+            ),
+        ];
 
-    if !exec.success() {
-        return Err(ApplicationError::FailedGitOperation());
+        for (input, expected) in cases {
+            let repo_urls = read_repo_urls(input.as_bytes()).unwrap();
+            assert_eq!(repo_urls, expected, "input: {:?}", input);
+        }
     }
 
-    eprintln!(
-        "\u{f058} Successfully cloned {}/{} into {}",
-        team, project, project_path
-    );
+    #[test]
+    fn test_check_conflicts() {
+        let cases = vec![
+            (vec!["org/repo", "--print-path-only"], true),
+            (vec!["--pick", "--print-path-only"], true),
+            (vec!["org/repo", "--json"], true),
+            (vec!["org/repo", "--json", "--print-path-only"], false),
+            (vec!["--init", "bash", "--print-path-only"], false),
+            (vec!["--completions", "zsh", "--print-path-only"], false),
+            (vec!["--complete-repos", "--print-path-only"], false),
+            (vec!["--parse", "org/repo", "--print-path-only"], false),
+            (vec!["--list", "--print-path-only"], false),
+            (vec!["--where", "org/repo", "--print-path-only"], false),
+            (vec!["org/repo", "--log-level", "warn", "-q"], false),
+            (vec!["org/repo", "--log-level", "warn", "-v"], false),
+        ];
 
-    if let Some(branch) = branch {
-        eprintln!("\u{f5c4} Checking out branch {}...", branch);
+        for (args, valid) in cases {
+            let matches = flags::options().parse(&args).unwrap();
+            assert_eq!(check_conflicts(&matches).is_ok(), valid, "args: {:?}", args);
+        }
+    }
 
-        let exec = Exec::cmd("git")
-            .args(&["checkout", &branch])
-            .cwd(&project_path)
-            .stdout(Redirection::None)
-            .stderr(Redirection::None)
-            .capture()
-            .map_err(ApplicationError::FailedCheckoutCommand)?;
+    #[test]
+    fn test_describe_batch() {
+        use Action::*;
+        let cases = vec![
+            (
+                vec![Cloned, Cloned],
+                "Successfully cloned all 2 repositories.",
+            ),
+            (
+                vec![Cloned, Updated, Skipped],
+                "Done with all 3 repositories: 1 cloned, 1 updated, 1 skipped.",
+            ),
+            (
+                vec![Skipped, Updated, Skipped],
+                "Done with all 3 repositories: 1 updated, 2 skipped.",
+            ),
+        ];
 
-        if !exec.success() {
-            return Err(ApplicationError::FailedGitOperation());
+        for (actions, expected) in cases {
+            assert_eq!(describe_batch(&actions), expected, "{:?}", actions);
         }
+    }
+
+    #[test]
+    fn test_split_git_args() {
+        let cases = vec![
+            (vec!["org/repo"], vec!["org/repo"], vec![]),
+            (vec!["org/repo", "--"], vec!["org/repo"], vec![]),
+            (
+                vec!["org/repo", "-q", "--", "--filter=blob:none", "--sparse"],
+                vec!["org/repo", "-q"],
+                vec!["--filter=blob:none", "--sparse"],
+            ),
+            (
+                vec!["org/repo", "--", "--", "-q"],
+                vec!["org/repo"],
+                vec!["--", "-q"],
+            ),
+        ];
 
-        eprintln!("\u{f5c4} Successfully checked out branch {}", branch);
+        for (input, expected_args, expected_git_args) in cases {
+            let args = input.iter().map(|arg| arg.to_string()).collect();
+            let (args, git_args) = split_git_args(args);
+            assert_eq!(args, expected_args, "input: {:?}", input);
+            assert_eq!(git_args, expected_git_args, "input: {:?}", input);
+        }
     }
 
-    println!("{}", project_path);
-    Ok(())
+    #[test]
+    fn test_split_args() {
+        let cases = vec![
+            ("", Some(vec![])),
+            ("   ", Some(vec![])),
+            ("--filter=blob:none", Some(vec!["--filter=blob:none"])),
+            (
+                "  --filter=blob:none   --no-tags ",
+                Some(vec!["--filter=blob:none", "--no-tags"]),
+            ),
+            (
+                "--config 'user.name=Jane Doe'",
+                Some(vec!["--config", "user.name=Jane Doe"]),
+            ),
+            (
+                r#"--config "core.sshCommand=ssh -i \"my key\"""#,
+                Some(vec!["--config", r#"core.sshCommand=ssh -i "my key""#]),
+            ),
+            (
+                r"--template=my\ templates",
+                Some(vec!["--template=my templates"]),
+            ),
+            ("--config ''", Some(vec!["--config", ""])),
+            ("--config 'unclosed", None),
+            ("--config \"unclosed", None),
+            ("trailing\\", None),
+        ];
+
+        for (input, expected) in cases {
+            let expected =
+                expected.map(|args| args.into_iter().map(String::from).collect::<Vec<_>>());
+            assert_eq!(split_args(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_is_truthy() {
+        let cases = vec![
+            ("1", true),
+            ("true", true),
+            ("yes", true),
+            ("TRUE", true),
+            (" Yes ", true),
+            ("0", false),
+            ("false", false),
+            ("no", false),
+            ("on", false),
+            ("", false),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(is_truthy(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_resolve_depth() {
+        let some = |depth: &str| Some(depth.to_string());
+        let cases = vec![
+            (None, false, None, None, Some(None)),
+            (None, false, some("5"), some("3"), Some(Some(5))),
+            (None, false, None, some("3"), Some(Some(3))),
+            (some("2"), false, some("5"), some("3"), Some(Some(2))),
+            (some("0"), false, some("5"), some("3"), Some(None)),
+            (None, true, some("5"), some("3"), Some(None)),
+            (some("0"), true, some("5"), None, Some(None)),
+            (None, false, some("0"), some("3"), Some(None)),
+            (some("2"), true, None, None, None),
+            (some("-1"), false, None, None, None),
+            (None, false, some("abc"), None, None),
+        ];
+
+        for (flag, full, config, env, expected) in cases {
+            let input = format!("{:?}", (&flag, full, &config, &env));
+            let depth = resolve_depth(flag, full, config, env).ok();
+            assert_eq!(depth, expected, "input: {}", input);
+        }
+    }
 }
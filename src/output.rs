@@ -0,0 +1,132 @@
+use std::cell::RefCell;
+use std::fmt::{self, Write as _};
+use std::io::{self, Write as _};
+use std::time::Duration;
+
+use subprocess::Redirection;
+
+use crate::color::{paint, Color};
+use crate::icon::Icon;
+
+/// How much is printed to stderr, from only errors up to every git command
+/// being run. Each level includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+// Output decides which status messages are shown and where the output of the
+// git commands goes. Errors and the final path are always printed regardless,
+// and stdout is reserved for the final path so shell functions can rely on
+// it. It also carries which git binary to run, how long each git command may
+// run and whether git may ask for credentials, since it's what every command
+// is run with, and whether status messages use plain ASCII markers. With a
+// buffer, messages are collected there instead and printed all at once when
+// it's dropped, so clones running at the same time don't mix theirs up.
+pub struct Output {
+    pub git: String,
+    pub level: LogLevel,
+    pub quiet_git: bool,
+    pub progress: bool,
+    pub timeout: Option<Duration>,
+    pub no_prompt: bool,
+    pub plain: bool,
+    pub buffer: Option<RefCell<String>>,
+}
+
+impl Output {
+    pub fn icon(&self, icon: Icon) -> &'static str {
+        icon.text(self.plain)
+    }
+
+    fn write(&self, message: fmt::Arguments) {
+        match &self.buffer {
+            Some(buffer) => {
+                let _ = writeln!(buffer.borrow_mut(), "{}", message);
+            }
+            None => eprintln!("{}", message),
+        }
+    }
+
+    fn log(&self, level: LogLevel, message: fmt::Arguments) {
+        if level <= self.level {
+            self.write(message);
+        }
+    }
+
+    // Printed whatever the level, like the plan of a dry run or the output of
+    // a hook
+    pub fn report(&self, message: fmt::Arguments) {
+        self.write(message);
+    }
+
+    pub fn is_buffered(&self) -> bool {
+        self.buffer.is_some()
+    }
+
+    pub fn status(&self, message: fmt::Arguments) {
+        self.log(LogLevel::Info, message);
+    }
+
+    pub fn success(&self, message: fmt::Arguments) {
+        self.status(format_args!("{}", paint(Color::Green, message)));
+    }
+
+    pub fn warning(&self, message: fmt::Arguments) {
+        self.log(
+            LogLevel::Warn,
+            format_args!("{}", paint(Color::Yellow, message)),
+        );
+    }
+
+    pub fn verbose(&self, message: fmt::Arguments) {
+        self.log(LogLevel::Debug, message);
+    }
+
+    // The output of git is shown along with the status messages, unless only
+    // git was asked to be quiet
+    fn hide_git(&self) -> bool {
+        self.quiet_git || self.level < LogLevel::Info
+    }
+
+    // With a buffer, the output of git is collected to be added to it, since
+    // it can't go straight to the terminal
+    pub fn buffers_git(&self) -> bool {
+        self.is_buffered() && !self.hide_git()
+    }
+
+    pub fn git_stdout(&self) -> Redirection {
+        if self.hide_git() || self.is_buffered() {
+            Redirection::Pipe
+        } else {
+            // Send it to stderr along with the rest of the output of git
+            Redirection::Merge
+        }
+    }
+
+    pub fn git_stderr(&self) -> Redirection {
+        if self.hide_git() {
+            Redirection::Pipe
+        } else if self.is_buffered() {
+            // Collected along with stdout, in the order git wrote them
+            Redirection::Merge
+        } else {
+            Redirection::None
+        }
+    }
+}
+
+impl Drop for Output {
+    // Everything collected goes out in a single write, so it stays together
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            let buffer = buffer.into_inner();
+            if !buffer.is_empty() {
+                let _ = io::stderr().lock().write_all(buffer.as_bytes());
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::BufRead;
+
+use gc_rust::ApplicationError;
+
+// How many candidates are listed at once, the rest are left for narrowing
+// the list down.
+const MAX_SHOWN: usize = 20;
+
+// Asks on stderr which of the repositories to pick, narrowing the list down
+// with whatever is typed until a number is given or a single one is left.
+pub fn pick(repos: &[String], mut input: impl BufRead) -> Result<String, ApplicationError> {
+    let mut candidates: Vec<&String> = repos.iter().collect();
+
+    loop {
+        for (index, repo) in candidates.iter().take(MAX_SHOWN).enumerate() {
+            eprintln!("{:>3}) {}", index + 1, repo);
+        }
+        if candidates.len() > MAX_SHOWN {
+            eprintln!("     ...and {} more", candidates.len() - MAX_SHOWN);
+        }
+        eprintln!(
+            "Type a number to pick a repository, or part of its name to narrow the list down:"
+        );
+
+        let mut line = String::new();
+        input
+            .read_line(&mut line)
+            .map_err(ApplicationError::FailedCaptureInput)?;
+        let query = line.trim();
+        if query.is_empty() {
+            return Err(ApplicationError::NothingPicked);
+        }
+
+        if let Ok(number) = query.parse::<usize>() {
+            if (1..=candidates.len().min(MAX_SHOWN)).contains(&number) {
+                return Ok(candidates[number - 1].clone());
+            }
+        }
+
+        let matching: Vec<&String> = candidates
+            .iter()
+            .copied()
+            .filter(|repo| fuzzy_match(repo, query))
+            .collect();
+        match matching.len() {
+            0 => eprintln!("No repositories match {:?}.", query),
+            1 => return Ok(matching[0].clone()),
+            _ => candidates = matching,
+        }
+    }
+}
+
+// Matches when every character of the query shows up in the name in the same
+// order, ignoring case, so "gcr" matches "patrickdappollonio/gc-rust".
+fn fuzzy_match(name: &str, query: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| name.any(|c| c == wanted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let cases = vec![
+            ("github.com/patrickdappollonio/gc-rust", "gc-rust", true),
+            ("github.com/patrickdappollonio/gc-rust", "GCR", true),
+            ("github.com/patrickdappollonio/gc-rust", "pdgc", true),
+            ("github.com/patrickdappollonio/gc-rust", "rustgc", false),
+            ("github.com/patrickdappollonio/gc-rust", "gitlab", false),
+            ("github.com/patrickdappollonio/gc-rust", "", true),
+        ];
+
+        for (name, query, expected) in cases {
+            assert_eq!(fuzzy_match(name, query), expected, "query: {:?}", query);
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let repos = vec![
+            "github.com/example/api".to_string(),
+            "github.com/example/web".to_string(),
+            "gitlab.com/group/api".to_string(),
+        ];
+
+        let cases = vec![
+            ("2\n", Some("github.com/example/web")),
+            ("web\n", Some("github.com/example/web")),
+            ("api\n2\n", Some("gitlab.com/group/api")),
+            ("api\nlab\n", Some("gitlab.com/group/api")),
+            ("nothing\nweb\n", Some("github.com/example/web")),
+            ("9\nweb\n", Some("github.com/example/web")),
+            ("\n", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            let picked = pick(&repos, input.as_bytes()).ok();
+            assert_eq!(picked.as_deref(), expected, "input: {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+const BAR_WIDTH: usize = 30;
+
+// Reads the output of git as it runs and draws its progress, like
+// "Receiving objects:  45% (450/1000)", as a single bar that's updated in
+// place. Any other line is printed as is, above the bar.
+pub fn render(mut stream: File) {
+    let mut stderr = io::stderr();
+    let mut pending = Vec::new();
+    let mut chunk = [0; 1024];
+    let mut drawn = false;
+
+    while let Ok(read) = stream.read(&mut chunk) {
+        if read == 0 {
+            break;
+        }
+        pending.extend_from_slice(&chunk[..read]);
+
+        // git ends progress updates with \r and everything else with \n
+        while let Some(end) = pending.iter().position(|b| *b == b'\r' || *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            let _ = match parse_progress(line) {
+                Some((phase, percent)) => {
+                    drawn = true;
+                    write!(stderr, "\r\x1b[2K{}", bar(phase, percent))
+                }
+                None => writeln!(stderr, "\r\x1b[2K{}", line),
+            };
+        }
+    }
+
+    if !pending.is_empty() {
+        let _ = writeln!(stderr, "\r\x1b[2K{}", String::from_utf8_lossy(&pending));
+    } else if drawn {
+        let _ = write!(stderr, "\r\x1b[2K");
+    }
+}
+
+// Finds the phase and percentage of a progress line, without the "remote: "
+// prefix of the phases that run on the server.
+fn parse_progress(line: &str) -> Option<(&str, u8)> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(": ")?;
+    let (percent, _) = rest.trim_start().split_once('%')?;
+    let percent = percent
+        .parse::<u8>()
+        .ok()
+        .filter(|percent| *percent <= 100)?;
+    Some((phase, percent))
+}
+
+fn bar(phase: &str, percent: u8) -> String {
+    let filled = BAR_WIDTH * percent as usize / 100;
+    format!(
+        "{} [{}{}] {}%",
+        phase,
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        percent
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress() {
+        let cases = vec![
+            (
+                "Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s",
+                Some(("Receiving objects", 45)),
+            ),
+            (
+                "remote: Compressing objects: 100% (20/20), done.",
+                Some(("Compressing objects", 100)),
+            ),
+            (
+                "Resolving deltas:   0% (0/12)",
+                Some(("Resolving deltas", 0)),
+            ),
+            ("Cloning into '/src/github.com/team/project'...", None),
+            ("remote: Enumerating objects: 42, done.", None),
+            ("fatal: repository not found", None),
+            ("Receiving objects: 250% (1/1)", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_progress(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(
+            bar("Receiving objects", 50),
+            format!(
+                "Receiving objects [{}{}] 50%",
+                "#".repeat(15),
+                " ".repeat(15)
+            )
+        );
+        assert_eq!(
+            bar("Resolving deltas", 100),
+            format!("Resolving deltas [{}] 100%", "#".repeat(30))
+        );
+    }
+}
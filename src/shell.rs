@@ -0,0 +1,464 @@
+use crate::flags::{Flag, Kind};
+
+// Flags that make gc-rust print something other than the destination of a
+// single clone, which the gc function passes straight through.
+const PASS_THROUGH: &[&str] = &[
+    "init",
+    "completions",
+    "complete-repos",
+    "parse",
+    "list",
+    "where",
+    "json",
+    "stdin",
+];
+
+// Shell functions that clone a repository with gc-rust and then cd into it,
+// meant to be loaded with `eval "$(gc-rust --init bash)"` or similar. Only a
+// single clone has a folder to go to, so anything else runs as is, with the
+// values of the flags skipped when counting the repositories.
+pub fn init_script(shell: &str, flags: &[Flag]) -> Option<String> {
+    match shell {
+        "bash" | "zsh" => Some(posix_init(flags)),
+        "fish" => Some(fish_init(flags)),
+        _ => None,
+    }
+}
+
+// The patterns matching the flags that pass through, and the ones matching the
+// flags followed by a value.
+fn init_patterns(flags: &[Flag]) -> (Vec<String>, Vec<String>) {
+    let mut pass_through = Vec::new();
+    let mut values = Vec::new();
+    for flag in flags {
+        let mut patterns = Vec::new();
+        if !flag.short.is_empty() {
+            patterns.push(format!("-{}", flag.short));
+        }
+        patterns.push(format!("--{}", flag.long));
+
+        if PASS_THROUGH.contains(&flag.long) {
+            if !matches!(flag.kind, Kind::Switch) {
+                patterns.push(format!("--{}=*", flag.long));
+            }
+            pass_through.extend(patterns);
+        } else if !matches!(flag.kind, Kind::Switch) {
+            values.extend(patterns);
+        }
+    }
+
+    (pass_through, values)
+}
+
+fn posix_init(flags: &[Flag]) -> String {
+    let (pass_through, values) = init_patterns(flags);
+
+    format!(
+        r#"gc() {{
+  local arg dir code skip= repos=0
+  for arg in "$@"; do
+    if [ -n "$skip" ]; then
+      skip=
+      continue
+    fi
+    case "$arg" in
+      --) break ;;
+      {}) command gc-rust "$@"; return ;;
+      {}) skip=1 ;;
+      -*) ;;
+      *) repos=$((repos + 1)) ;;
+    esac
+  done
+
+  if [ "$repos" -gt 1 ]; then
+    command gc-rust "$@"
+    return
+  fi
+
+  dir="$(command gc-rust --print-path-only "$@")"
+  code=$?
+  if [ -d "$dir" ]; then
+    cd "$dir" || return
+  fi
+  return $code
+}}
+"#,
+        pass_through.join("|"),
+        values.join("|")
+    )
+}
+
+fn fish_init(flags: &[Flag]) -> String {
+    let quote = |patterns: Vec<String>| {
+        patterns
+            .iter()
+            .map(|pattern| format!("'{}'", pattern))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let (pass_through, values) = init_patterns(flags);
+
+    format!(
+        r#"function gc
+    set -l repos 0
+    set -l skip 0
+    for arg in $argv
+        if test $skip -eq 1
+            set skip 0
+            continue
+        end
+        switch $arg
+            case '--'
+                break
+            case {}
+                command gc-rust $argv
+                return
+            case {}
+                set skip 1
+            case '-*'
+            case '*'
+                set repos (math $repos + 1)
+        end
+    end
+
+    if test $repos -gt 1
+        command gc-rust $argv
+        return
+    end
+
+    set -l dir (command gc-rust --print-path-only $argv)
+    set -l code $status
+    if test -d "$dir"
+        cd $dir
+    end
+    return $code
+end
+"#,
+        quote(pass_through),
+        quote(values)
+    )
+}
+
+// Completion scripts for both gc-rust and the gc function from init_script,
+// meant to be saved into the completions directory of the shell.
+pub fn completion_script(shell: &str, flags: &[Flag]) -> Option<String> {
+    match shell {
+        "bash" => Some(bash_completion(flags)),
+        "zsh" => Some(zsh_completion(flags)),
+        "fish" => Some(fish_completion(flags)),
+        _ => None,
+    }
+}
+
+fn bash_completion(flags: &[Flag]) -> String {
+    let mut names = Vec::new();
+    let mut cases = String::new();
+    for flag in flags.iter().filter(|flag| !flag.hidden) {
+        let mut patterns = Vec::new();
+        if !flag.short.is_empty() {
+            patterns.push(format!("-{}", flag.short));
+        }
+        patterns.push(format!("--{}", flag.long));
+        names.extend(patterns.iter().cloned());
+
+        match flag.kind {
+            Kind::Switch => {}
+            Kind::Path(_) => cases.push_str(&format!(
+                "    {})\n      COMPREPLY=($(compgen -f -- \"$cur\"))\n      return\n      ;;\n",
+                patterns.join("|")
+            )),
+            _ if !flag.values.is_empty() => cases.push_str(&format!(
+                "    {})\n      COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n      return\n      ;;\n",
+                patterns.join("|"),
+                flag.values.join(" ")
+            )),
+            _ => cases.push_str(&format!(
+                "    {})\n      return\n      ;;\n",
+                patterns.join("|")
+            )),
+        }
+    }
+
+    format!(
+        r#"_gc_rust() {{
+  local cur prev
+  cur="${{COMP_WORDS[COMP_CWORD]}}"
+  prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+  case "$prev" in
+{}  esac
+  if [[ "$cur" == -* ]]; then
+    COMPREPLY=($(compgen -W "{}" -- "$cur"))
+  else
+    COMPREPLY=($(compgen -W "$(command gc-rust --complete-repos 2>/dev/null)" -- "$cur"))
+  fi
+}}
+complete -F _gc_rust gc-rust gc
+"#,
+        cases,
+        names.join(" ")
+    )
+}
+
+fn zsh_completion(flags: &[Flag]) -> String {
+    let mut specs = String::new();
+    for flag in flags.iter().filter(|flag| !flag.hidden) {
+        let description = flag
+            .description
+            .replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]");
+        let value = match flag.kind {
+            Kind::Switch => String::new(),
+            Kind::Path(hint) => format!(":{}:_files", hint),
+            Kind::Value(hint) | Kind::Multi(hint) if !flag.values.is_empty() => {
+                format!(":{}:({})", hint, flag.values.join(" "))
+            }
+            Kind::Value(hint) | Kind::Multi(hint) => format!(":{}:", hint),
+        };
+        let repeat = if matches!(flag.kind, Kind::Multi(_)) {
+            "*"
+        } else {
+            ""
+        };
+
+        if flag.short.is_empty() {
+            specs.push_str(&format!(
+                "    '{}--{}[{}]{}' \\\n",
+                repeat, flag.long, description, value
+            ));
+        } else {
+            specs.push_str(&format!(
+                "    '{}(-{} --{})'{{-{},--{}}}'[{}]{}' \\\n",
+                repeat, flag.short, flag.long, flag.short, flag.long, description, value
+            ));
+        }
+    }
+
+    format!(
+        r#"#compdef gc-rust gc
+
+_gc-rust-repos() {{
+  local -a repos
+  repos=(${{(f)"$(command gc-rust --complete-repos 2>/dev/null)"}})
+  compadd -a repos
+}}
+
+_gc-rust() {{
+  _arguments -s \
+{}    '*:repository:_gc-rust-repos'
+}}
+
+if [ "$funcstack[1]" = "_gc-rust" ]; then
+  _gc-rust "$@"
+else
+  compdef _gc-rust gc-rust gc
+fi
+"#,
+        specs
+    )
+}
+
+fn fish_completion(flags: &[Flag]) -> String {
+    let mut script = String::new();
+    for flag in flags.iter().filter(|flag| !flag.hidden) {
+        script.push_str("complete -c gc-rust");
+        if !flag.short.is_empty() {
+            script.push_str(&format!(" -s {}", flag.short));
+        }
+        script.push_str(&format!(" -l {}", flag.long));
+
+        match flag.kind {
+            Kind::Switch => {}
+            Kind::Path(_) => script.push_str(" -r -F"),
+            _ if !flag.values.is_empty() => {
+                script.push_str(&format!(" -x -a '{}'", flag.values.join(" ")))
+            }
+            _ => script.push_str(" -x"),
+        }
+
+        let description = flag.description.replace('\\', "\\\\").replace('\'', "\\'");
+        script.push_str(&format!(" -d '{}'\n", description));
+    }
+
+    script.push_str("complete -c gc-rust -f -a '(command gc-rust --complete-repos 2>/dev/null)'\n");
+    script.push_str("complete -c gc -w gc-rust\n");
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use subprocess::Exec;
+
+    use super::*;
+
+    #[test]
+    fn test_init_script() {
+        let flags = crate::flags::FLAGS;
+        let cases = vec![
+            (
+                "bash",
+                vec![
+                    "|--init|--init=*|",
+                    "-b|--branch|",
+                    "gc-rust --print-path-only",
+                ],
+            ),
+            (
+                "zsh",
+                vec![
+                    "|--init|--init=*|",
+                    "-b|--branch|",
+                    "gc-rust --print-path-only",
+                ],
+            ),
+            (
+                "fish",
+                vec![
+                    " '--init' '--init=*' ",
+                    "'-b' '--branch' ",
+                    "gc-rust --print-path-only",
+                ],
+            ),
+        ];
+
+        for (shell, expected) in cases {
+            let script = init_script(shell, flags).unwrap();
+            for line in expected {
+                assert!(script.contains(line), "{}: {:?} in {}", shell, line, script);
+            }
+        }
+
+        assert!(init_script("powershell", flags).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_init_script_bash() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stands in for gc-rust: the path of the test folder is the only
+        // thing it prints for a single clone, its arguments otherwise
+        let dir = env::temp_dir().join(format!("gc-rust-test-shell-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fake = dir.join("gc-rust");
+        fs::write(
+            &fake,
+            "#!/bin/sh\nif [ \"$1\" = --print-path-only ]; then echo \"$GC_TEST_DIR\"; else echo \"$@\"; fi\n",
+        )
+        .unwrap();
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let init = init_script("bash", crate::flags::FLAGS).unwrap();
+        let dir = dir.display().to_string();
+        let cases = vec![
+            ("gc org/repo", format!("{}\n", dir)),
+            ("gc -b main org/repo", format!("{}\n", dir)),
+            ("gc --pick", format!("{}\n", dir)),
+            ("gc --list", "--list\n/\n".to_string()),
+            (
+                "gc --completions bash",
+                "--completions bash\n/\n".to_string(),
+            ),
+            ("gc --where org/repo", "--where org/repo\n/\n".to_string()),
+            ("gc org/a org/b", "org/a org/b\n/\n".to_string()),
+        ];
+
+        for (command, expected) in cases {
+            let script = format!("{}\ncd / && {} && pwd", init, command);
+            let exec = Exec::cmd("bash")
+                .arg("-c")
+                .arg(&script)
+                .env("PATH", format!("{}:{}", dir, env::var("PATH").unwrap()))
+                .env("GC_TEST_DIR", &dir)
+                .capture()
+                .unwrap();
+            assert_eq!(exec.stdout_str(), expected, "command: {}", command);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_completion_script() {
+        let flags = [
+            Flag {
+                short: "b",
+                long: "branch",
+                description: "set the branch",
+                kind: Kind::Value("BRANCH"),
+                values: &[],
+                hidden: false,
+            },
+            Flag {
+                short: "",
+                long: "protocol",
+                description: "set the [protocol]",
+                kind: Kind::Value("PROTOCOL"),
+                values: &["ssh", "https"],
+                hidden: false,
+            },
+            Flag {
+                short: "",
+                long: "complete-repos",
+                description: "hidden",
+                kind: Kind::Switch,
+                values: &[],
+                hidden: true,
+            },
+            Flag {
+                short: "",
+                long: "bare",
+                description: "don't check out",
+                kind: Kind::Switch,
+                values: &[],
+                hidden: false,
+            },
+        ];
+
+        let cases = vec![
+            (
+                "bash",
+                vec![
+                    "-b|--branch)\n      return",
+                    "compgen -W \"ssh https\"",
+                    "compgen -W \"-b --branch --protocol --bare\"",
+                    "gc-rust --complete-repos",
+                    "complete -F _gc_rust gc-rust gc",
+                ],
+            ),
+            (
+                "zsh",
+                vec![
+                    "'(-b --branch)'{-b,--branch}'[set the branch]:BRANCH:'",
+                    "'--protocol[set the \\[protocol\\]]:PROTOCOL:(ssh https)'",
+                    "'--bare[don'\\''t check out]'",
+                    "'*:repository:_gc-rust-repos'",
+                    "compdef _gc-rust gc-rust gc",
+                ],
+            ),
+            (
+                "fish",
+                vec![
+                    "complete -c gc-rust -s b -l branch -x -d 'set the branch'",
+                    "complete -c gc-rust -l protocol -x -a 'ssh https'",
+                    "complete -c gc-rust -l bare -d 'don\\'t check out'",
+                    "complete -c gc-rust -f -a '(command gc-rust --complete-repos 2>/dev/null)'",
+                    "complete -c gc -w gc-rust",
+                ],
+            ),
+        ];
+
+        for (shell, expected) in cases {
+            let script = completion_script(shell, &flags).unwrap();
+            for line in expected {
+                assert!(script.contains(line), "{}: {:?} in {}", shell, line, script);
+            }
+            assert!(!script.contains("hidden"), "{}: {}", shell, script);
+        }
+
+        assert!(completion_script("powershell", &flags).is_none());
+    }
+}